@@ -0,0 +1,87 @@
+//! Compares `CFG::parse` with the interned grammar on a long token stream.
+//! Run with `cargo bench`.
+
+use ll1_parser::context_free_grammar;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+enum Token {
+    Add,
+    Mul,
+    LeftBracket,
+    RightBracket,
+    Num,
+}
+
+/// `(d a d) m d a (d a d) m d a ...` with `n` groups.
+fn tokens(n: usize) -> Vec<Token> {
+    use Token::{Add, LeftBracket, Mul, Num, RightBracket};
+    let mut tokens = Vec::new();
+    for i in 0..n {
+        if i > 0 {
+            tokens.push(Add);
+        }
+        tokens.extend(vec![LeftBracket, Num, Add, Num, RightBracket, Mul, Num]);
+    }
+    tokens
+}
+
+fn time<R, F: FnMut() -> R>(name: &str, tokens: usize, mut f: F) {
+    let runs = 5;
+    let mut best = Duration::MAX;
+    for _ in 0..runs {
+        let begin = Instant::now();
+        let r = f();
+        best = best.min(begin.elapsed());
+        drop(r);
+    }
+    println!(
+        "{:<24} {:>10.2?} {:>8.1} ns/token",
+        name,
+        best,
+        best.as_nanos() as f64 / tokens as f64
+    );
+}
+
+fn bench() {
+    let c = context_free_grammar!(
+        terminals: {
+            a = Token::Add,
+            m = Token::Mul,
+            l = Token::LeftBracket,
+            r = Token::RightBracket,
+            d = Token::Num
+        }
+        rules: {
+            E => T A;
+            A => a T A | ;
+            T => F B;
+            B => m F B | ;
+            F => l E r | d;
+        }
+        start: E
+    );
+    for n in [1_000, 10_000, 50_000] {
+        let tokens = tokens(n);
+        println!("{} tokens", tokens.len());
+        time("CFG::parse", tokens.len(), || c.parse(&tokens).unwrap());
+        let interned = c.intern().unwrap();
+        time("InternedCFG::parse", tokens.len(), || {
+            interned.parse(&tokens).unwrap()
+        });
+        time("InternedCFG::derivation", tokens.len(), || {
+            interned.parse_derivation(&tokens).unwrap()
+        });
+        time("CFG::intern", tokens.len(), || c.intern().unwrap());
+    }
+}
+
+fn main() {
+    // The trees are as deep as the token stream is long.
+    std::thread::Builder::new()
+        .stack_size(1 << 30)
+        .spawn(bench)
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,486 @@
+use crate::cfg::{Assoc, Production, Symbol, CFG, EPSILON};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::mem::discriminant;
+
+#[derive(Debug, PartialEq)]
+pub struct GrammarError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for GrammarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for GrammarError {}
+
+/// A terminal name that does not appear in the grammar.
+#[derive(Debug, PartialEq)]
+pub struct UnknownTerminal(pub String);
+
+impl Display for UnknownTerminal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown terminal {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownTerminal {}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Lexeme {
+    Name(String),
+    Directive(String),
+    Arrow,
+    Bar,
+    Semi,
+    Epsilon,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    lexeme: Lexeme,
+    line: usize,
+    column: usize,
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && c != '|' && c != ';'
+}
+
+fn tokenize(src: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (l, line) in src.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let chars: Vec<_> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let column = i + 1;
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let lexeme = if c == '|' {
+                i += 1;
+                Lexeme::Bar
+            } else if c == ';' {
+                i += 1;
+                Lexeme::Semi
+            } else {
+                let begin = i;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[begin..i].iter().collect();
+                match word.as_str() {
+                    "->" | "=>" | "::=" => Lexeme::Arrow,
+                    "ε" | EPSILON => Lexeme::Epsilon,
+                    _ if word.starts_with('%') => Lexeme::Directive(word[1..].to_string()),
+                    _ => Lexeme::Name(word),
+                }
+            };
+            tokens.push(Token {
+                lexeme,
+                line: l + 1,
+                column,
+            });
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Lexeme> {
+        self.tokens.get(self.pos).map(|t| &t.lexeme)
+    }
+
+    fn peek2(&self) -> Option<&Lexeme> {
+        self.tokens.get(self.pos + 1).map(|t| &t.lexeme)
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let t = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(t.lexeme)
+    }
+
+    fn error<R>(&self, message: String) -> Result<R, GrammarError> {
+        let (line, column) = match self.tokens.get(self.pos) {
+            Some(t) => (t.line, t.column),
+            None => (self.line, 1),
+        };
+        Err(GrammarError {
+            line,
+            column,
+            message,
+        })
+    }
+
+    fn at_rule_start(&self) -> bool {
+        matches!(
+            (self.peek(), self.peek2()),
+            (Some(Lexeme::Name(_)), Some(Lexeme::Arrow))
+        )
+    }
+
+    fn at_new_line(&self) -> bool {
+        let prev = &self.tokens[self.pos - 1];
+        prev.line != self.tokens[self.pos].line && matches!(prev.lexeme, Lexeme::Name(_))
+    }
+
+    fn names(&mut self) -> Vec<(String, usize)> {
+        let mut names = Vec::new();
+        while let Some(Lexeme::Name(n)) = self.peek() {
+            if self.at_rule_start() || self.at_new_line() {
+                break;
+            }
+            names.push((n.clone(), self.pos));
+            self.pos += 1;
+        }
+        names
+    }
+}
+
+struct RawRule {
+    left: String,
+    left_pos: usize,
+    right: Vec<(String, usize)>,
+    epsilon: bool,
+}
+
+pub struct Grammar {
+    pub terminals: Vec<String>,
+    pub rules: Vec<Production>,
+    pub start: String,
+    /// The precedence levels from lowest to highest.
+    pub precedence: Vec<(Assoc, Vec<String>)>,
+}
+
+/// Parses a grammar written as
+///
+/// ```text
+/// %start E
+/// E -> T A ;
+/// A -> a T A | ε
+/// ```
+///
+/// Every symbol that appears on the left of `->` is a variable, the others are
+/// terminals unless `%terminals` lists them explicitly. `ε` or `#` (or an empty
+/// alternative) is the empty string and `//` starts a comment. A rule ends at
+/// `;` or at the end of the line, but may go on after a trailing `->` or `|`.
+/// Without `%start`, the first rule's variable is the start. Each `%left`,
+/// `%right` or `%nonassoc` line declares a precedence level for terminals,
+/// binding tighter than the lines above it.
+pub fn parse_grammar(src: &str) -> Result<Grammar, GrammarError> {
+    let tokens = tokenize(src);
+    let mut parser = Parser {
+        line: src.lines().count().max(1),
+        tokens,
+        pos: 0,
+    };
+    let mut start: Option<(String, usize)> = None;
+    let mut declared: Option<Vec<String>> = None;
+    let mut raw_rules = Vec::new();
+    let mut levels = Vec::new();
+    while let Some(lexeme) = parser.peek().cloned() {
+        match lexeme {
+            Lexeme::Semi => {
+                parser.next();
+            }
+            Lexeme::Directive(d) => {
+                parser.next();
+                let args = parser.names();
+                match d.as_str() {
+                    "start" => {
+                        if args.len() != 1 {
+                            return parser.error("%start takes exactly one variable".into());
+                        }
+                        start = Some(args[0].clone());
+                    }
+                    "terminals" => {
+                        declared
+                            .get_or_insert_with(Vec::new)
+                            .extend(args.into_iter().map(|a| a.0));
+                    }
+                    "left" | "right" | "nonassoc" => {
+                        let assoc = match d.as_str() {
+                            "left" => Assoc::Left,
+                            "right" => Assoc::Right,
+                            _ => Assoc::Nonassoc,
+                        };
+                        if args.is_empty() {
+                            parser.pos -= 1;
+                            return parser.error(format!("%{} takes at least one terminal", d));
+                        }
+                        levels.push((assoc, args));
+                    }
+                    _ => {
+                        parser.pos -= args.len() + 1;
+                        return parser.error(format!("Unknown directive %{}", d));
+                    }
+                }
+            }
+            Lexeme::Name(left) => {
+                let left_pos = parser.pos;
+                parser.next();
+                if parser.next() != Some(Lexeme::Arrow) {
+                    parser.pos -= 1;
+                    return parser.error(format!("Expected -> after {}", left));
+                }
+                loop {
+                    let right = parser.names();
+                    let epsilon = right.is_empty() && parser.peek() == Some(&Lexeme::Epsilon);
+                    if epsilon {
+                        parser.next();
+                    }
+                    raw_rules.push(RawRule {
+                        left: left.clone(),
+                        left_pos,
+                        right,
+                        epsilon,
+                    });
+                    match parser.peek() {
+                        Some(Lexeme::Bar) => {
+                            parser.next();
+                        }
+                        Some(Lexeme::Semi) | None => break,
+                        Some(Lexeme::Directive(_)) | Some(Lexeme::Name(_)) => break,
+                        Some(Lexeme::Epsilon) => {
+                            return parser
+                                .error("ε must be the only symbol of an alternative".into())
+                        }
+                        Some(Lexeme::Arrow) => return parser.error("Unexpected ->".into()),
+                    }
+                }
+            }
+            _ => return parser.error("Expected a rule".into()),
+        }
+    }
+
+    let variables: Vec<String> = raw_rules.iter().fold(Vec::new(), |mut v, r| {
+        if !v.contains(&r.left) {
+            v.push(r.left.clone());
+        }
+        v
+    });
+    if variables.is_empty() {
+        return parser.error("Grammar has no rules".into());
+    }
+    let mut terminals = declared.clone().unwrap_or_default();
+    if let Some(raw) = raw_rules.iter().find(|r| terminals.contains(&r.left)) {
+        parser.pos = raw.left_pos;
+        return parser.error(format!("Terminal {} has rules", raw.left));
+    }
+    let mut rules = Vec::new();
+    for raw in raw_rules {
+        let mut right = Vec::new();
+        for (name, pos) in raw.right {
+            if variables.contains(&name) {
+                right.push(Symbol::Variable(name));
+                continue;
+            }
+            if !terminals.contains(&name) {
+                if declared.is_some() {
+                    parser.pos = pos;
+                    return parser.error(format!("Undeclared terminal {}", name));
+                }
+                terminals.push(name.clone());
+            }
+            right.push(Symbol::Terminal(name));
+        }
+        if right.is_empty() || raw.epsilon {
+            right.push(Symbol::Epsilon);
+        }
+        rules.push(Production {
+            left: raw.left,
+            right,
+            action: None,
+        });
+    }
+    let mut precedence = Vec::new();
+    for (assoc, args) in levels {
+        for (name, pos) in args.iter() {
+            if variables.contains(name) {
+                parser.pos = *pos;
+                return parser.error(format!("Precedence declared for variable {}", name));
+            }
+            if !terminals.contains(name) {
+                parser.pos = *pos;
+                return parser.error(format!("Undeclared terminal {}", name));
+            }
+        }
+        precedence.push((assoc, args.into_iter().map(|a| a.0).collect()));
+    }
+    let start = match start {
+        Some((s, pos)) => {
+            if !variables.contains(&s) {
+                parser.pos = pos;
+                return parser.error(format!("Start symbol {} has no rules", s));
+            }
+            s
+        }
+        None => variables[0].clone(),
+    };
+    Ok(Grammar {
+        terminals,
+        rules,
+        start,
+        precedence,
+    })
+}
+
+impl<T, V> CFG<T, V> {
+    /// Loads a grammar at runtime, see [`parse_grammar`] for the format. The
+    /// terminals are not bound to any token yet, use `bind_terminal` before
+    /// calling `parse`.
+    pub fn from_bnf(src: &str) -> Result<CFG<T, V>, GrammarError> {
+        let g = parse_grammar(src)?;
+        let mut cfg = CFG {
+            discriminant2terminal: HashMap::new(),
+            terminals: g.terminals,
+            rules: g.rules,
+            start: g.start,
+            precedence: HashMap::new(),
+            actions: Vec::new(),
+        };
+        for (assoc, terminals) in g.precedence {
+            let terminals: Vec<_> = terminals.iter().map(|t| t.as_str()).collect();
+            cfg.add_precedence(assoc, &terminals)
+                .expect("parse_grammar checks the precedence terminals");
+        }
+        Ok(cfg)
+    }
+
+    pub fn bind_terminal(&mut self, terminal: &str, token: &T) -> Result<(), UnknownTerminal> {
+        if !self.terminals.iter().any(|t| t == terminal) {
+            return Err(UnknownTerminal(terminal.to_string()));
+        }
+        #[allow(enum_intrinsics_non_enums)]
+        self.discriminant2terminal
+            .insert(discriminant(token), terminal.to_string());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_grammar() {
+    let g = parse_grammar(
+        "// expression grammar
+        E -> T A ; A -> a T A | ε
+        T -> F B
+        B -> m F B
+           |
+        F -> l E r | d",
+    )
+    .unwrap();
+    assert_eq!(g.start, "E");
+    assert_eq!(g.terminals, vec!["a", "m", "l", "r", "d"]);
+    assert_eq!(
+        g.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec![
+            "E => T A",
+            "A => a T A",
+            "A => #",
+            "T => F B",
+            "B => m F B",
+            "B => #",
+            "F => l E r",
+            "F => d",
+        ]
+    );
+
+    let g = parse_grammar("%start S\n%terminals x\nA -> x\nS -> A A").unwrap();
+    assert_eq!(g.start, "S");
+    assert_eq!(g.rules[1].to_string(), "S => A A");
+
+    let g = parse_grammar("%left + -\n%right ^\nE -> E + E | E - E | E ^ E | n").unwrap();
+    assert_eq!(g.terminals, vec!["+", "-", "^", "n"]);
+    assert_eq!(
+        g.precedence,
+        vec![
+            (Assoc::Left, vec!["+".to_string(), "-".to_string()]),
+            (Assoc::Right, vec!["^".to_string()])
+        ]
+    );
+}
+
+#[test]
+fn test_parse_grammar_errors() {
+    let err = |src: &str| parse_grammar(src).err().unwrap();
+    assert_eq!(
+        err("E -> a\nF b"),
+        GrammarError {
+            line: 2,
+            column: 3,
+            message: "Expected -> after F".into()
+        }
+    );
+    assert_eq!(err("").message, "Grammar has no rules");
+    assert_eq!(
+        err("%start S\nE -> a").to_string(),
+        "1:8: Start symbol S has no rules"
+    );
+    assert_eq!(
+        err("%terminals a\nE -> a b").to_string(),
+        "2:8: Undeclared terminal b"
+    );
+    assert_eq!(
+        err("%terminals E\nE -> a").to_string(),
+        "2:1: Terminal E has rules"
+    );
+    assert_eq!(
+        err("%prec a\nE -> a").to_string(),
+        "1:1: Unknown directive %prec"
+    );
+    assert_eq!(
+        err("%left\nE -> a").to_string(),
+        "1:1: %left takes at least one terminal"
+    );
+    assert_eq!(
+        err("%right a E\nE -> a").to_string(),
+        "1:10: Precedence declared for variable E"
+    );
+    assert_eq!(
+        err("%left b\nE -> a").to_string(),
+        "1:7: Undeclared terminal b"
+    );
+    assert_eq!(
+        err("E -> a ε").to_string(),
+        "1:8: ε must be the only symbol of an alternative"
+    );
+}
+
+#[test]
+fn test_from_bnf() {
+    #[derive(Debug)]
+    enum Token {
+        Id,
+        Comma,
+    }
+    let mut c: CFG<Token> = CFG::from_bnf("L -> id R\nR -> , id R | ε").unwrap();
+    c.bind_terminal("id", &Token::Id).unwrap();
+    c.bind_terminal(",", &Token::Comma).unwrap();
+    assert!(c.parse(&[Token::Id, Token::Comma, Token::Id]).is_ok());
+    assert!(c.parse(&[Token::Id, Token::Id]).is_err());
+    assert_eq!(
+        c.bind_terminal(";", &Token::Comma),
+        Err(UnknownTerminal(";".into()))
+    );
+
+    let c: CFG<Token> = CFG::from_bnf("%left ,\nL -> L , L | id").unwrap();
+    assert_eq!(c.precedence[","], (0, Assoc::Left));
+    assert!(c.get_slr_table().is_ok());
+}
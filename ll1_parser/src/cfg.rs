@@ -1,137 +1,451 @@
+use crate::bnf::UnknownTerminal;
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hash;
 use std::mem::{discriminant, Discriminant};
 
 const NEW_VAR_SUFFIX: &str = "'";
-const EPSILON: &str = "#";
+pub const EPSILON: &str = "#";
+pub const END_OF_INPUT: &str = "$";
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub enum Symbol {
     Variable(String),
     Terminal(String),
     Epsilon,
+    EndOfInput,
 }
 
-#[derive(Debug, Clone)]
+/// `action` is the index in `CFG::actions` of the semantic action computing
+/// the value of the production.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Production {
     pub left: String,
     pub right: Vec<Symbol>,
+    pub action: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+    Nonassoc,
 }
 
+/// `precedence` maps a terminal to its level and associativity, a higher
+/// level binding tighter. LR tables use it to resolve shift/reduce conflicts.
+/// `actions` holds the semantic actions the productions refer to, see
+/// `CFG::eval_tree`.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
-pub struct CFG<T> {
+pub struct CFG<T, V = ()> {
     pub discriminant2terminal: HashMap<Discriminant<T>, String>,
     pub terminals: Vec<String>,
     pub rules: Vec<Production>,
     pub start: String,
+    pub precedence: HashMap<String, (usize, Assoc)>,
+    pub actions: Vec<SemanticAction<T, V>>,
+}
+
+/// A child handed to a semantic action: the token matched by a terminal or
+/// the value computed for a variable. ε-productions get no children.
+#[derive(Debug)]
+pub enum Arg<'a, T, V> {
+    Token(&'a T),
+    Value(V),
+}
+
+impl<'a, T, V> Arg<'a, T, V> {
+    /// Panics if the child is a variable.
+    pub fn token(&self) -> &'a T {
+        match self {
+            Arg::Token(t) => t,
+            Arg::Value(_) => panic!("expected a token but found a value"),
+        }
+    }
+
+    /// Panics if the child is a terminal.
+    pub fn value(&self) -> &V {
+        match self {
+            Arg::Value(v) => v,
+            Arg::Token(_) => panic!("expected a value but found a token"),
+        }
+    }
+
+    /// Panics if the child is a terminal.
+    pub fn into_value(self) -> V {
+        match self {
+            Arg::Value(v) => v,
+            Arg::Token(_) => panic!("expected a value but found a token"),
+        }
+    }
+}
+
+type ActionFn<T, V> = dyn Fn(Vec<Arg<'_, T, V>>) -> V;
+
+/// Computes the value of a production from the children of its node.
+pub struct SemanticAction<T, V>(Box<ActionFn<T, V>>);
+
+impl<T, V> Debug for SemanticAction<T, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SemanticAction")
+    }
+}
+
+/// Wraps a closure for `CFG::actions`, letting the compiler infer the types of
+/// its arguments.
+pub fn action<T, V, F>(f: F) -> SemanticAction<T, V>
+where
+    F: Fn(Vec<Arg<'_, T, V>>) -> V + 'static,
+{
+    SemanticAction(Box::new(f))
 }
 
 #[derive(Debug)]
 pub struct Sets(HashMap<String, HashSet<Symbol>>);
 
+/// The LL(1) table, its cells holding indices into `rules`. `variables` and
+/// `terminals` (with `$` last) give the order of rows and columns when
+/// rendered.
+#[derive(Debug)]
+pub struct Table<'a> {
+    pub cells: HashMap<(String, Symbol), usize>,
+    pub rules: &'a [Production],
+    pub variables: Vec<String>,
+    pub terminals: Vec<Symbol>,
+}
+
+/// An LL(1) table whose cells may hold several productions.
 #[derive(Debug)]
-pub struct Table<'a>(HashMap<(String, Symbol), &'a Production>);
+pub struct MultiTable<'a> {
+    pub cells: HashMap<(String, Symbol), Vec<usize>>,
+    pub rules: &'a [Production],
+    pub variables: Vec<String>,
+    pub terminals: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    FirstFirst,
+    FirstFollow,
+}
+
+/// Two productions competing for the same cell of the LL(1) table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub variable: String,
+    pub lookahead: Symbol,
+    pub productions: (Production, Production),
+    pub kind: ConflictKind,
+}
+
+/// Why `left_factor` or `left_recursion_eliminate` left the rules untouched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RewriteError {
+    /// Left recursion of the variable survives the rewrite, the variable
+    /// deriving itself after a nullable prefix as in `A => B A x` with
+    /// `B =>* ε`.
+    HiddenLeftRecursion(String),
+    /// The production has a semantic action, which would not fit the
+    /// productions it is rewritten into.
+    Action(Production),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The token at `index` (or `$` past the end) is not one of `expected`.
+    UnexpectedToken {
+        index: usize,
+        found: Symbol,
+        expected: Vec<Symbol>,
+    },
+    /// The token at `index` is not bound to any terminal.
+    UnknownToken { index: usize },
+    /// The grammar has conflicts, see `CFG::get_table`.
+    NotLL1(Vec<Conflict>),
+}
+
+impl ParseError {
+    /// The index of the offending token, `None` when the grammar is at fault.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            ParseError::UnexpectedToken { index, .. } | ParseError::UnknownToken { index } => {
+                Some(*index)
+            }
+            ParseError::NotLL1(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseAction<'a> {
+    Match(Symbol),
+    Expand(&'a Production),
+    Accept,
+    Error(ParseError),
+}
+
+/// One row of a parse trace: the stack (bottom first) and the remaining input
+/// before `action` is taken. Tokens not bound to a terminal are left out.
+#[derive(Debug, Clone)]
+pub struct ParseStep<'a> {
+    pub stack: Vec<Symbol>,
+    pub input: Vec<Symbol>,
+    pub action: ParseAction<'a>,
+}
+
+#[derive(Debug)]
+pub struct ParseTrace<'a>(pub Vec<ParseStep<'a>>);
 
 #[derive(Debug)]
 pub enum Tree<'a, T: 'a> {
-    Epslion,
-    Leaf(&'a T),
+    Epsilon,
+    /// The token at `index` of the input.
+    Leaf {
+        index: usize,
+        token: &'a T,
+    },
+    /// A variable expanded with `rules[production]`. `production` is `None`
+    /// for a variable error recovery gave up on and for a terminal missing
+    /// from the input, which have no children.
     Node {
         name: String,
+        production: Option<usize>,
         nodes: Vec<Tree<'a, T>>,
     },
 }
 
+// Leaves only borrow the tokens, so a tree can be cloned whatever `T` is.
+impl<T> Clone for Tree<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Tree::Epsilon => Tree::Epsilon,
+            Tree::Leaf { index, token } => Tree::Leaf {
+                index: *index,
+                token,
+            },
+            Tree::Node {
+                name,
+                production,
+                nodes,
+            } => Tree::Node {
+                name: name.clone(),
+                production: *production,
+                nodes: nodes.clone(),
+            },
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! to_symbol {
     (@ $terms:ident $e:tt) => {{
         let s = String::from(stringify!($e));
         if $terms.contains(&s) {
-            crate::cfg::Symbol::Terminal(s)
+            $crate::cfg::Symbol::Terminal(s)
         } else {
-            crate::cfg::Symbol::Variable(s)
+            $crate::cfg::Symbol::Variable(s)
         }
     }};
 }
 
+/// Collects the productions of `rules: {...}` and the semantic actions given
+/// in braces after them.
 #[macro_export]
 macro_rules! productions {
-    (@ $terms:ident $vec:ident) => {
+    (@ $terms:ident $vec:ident $acts:ident) => {
+
+    };
+
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => ; $($t:tt)*) => {
+        $vec.push($crate::cfg::Production{left: String::from(stringify!($v)), right: vec![$crate::cfg::Symbol::Epsilon], action: None});
+        $crate::productions!(@ $terms $vec $acts $($t)*);
+    };
+
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => {$($f:tt)*} ; $($t:tt)*) => {
+        $crate::productions!(@action $vec $acts $v vec![$crate::cfg::Symbol::Epsilon], $($f)*);
+        $crate::productions!(@ $terms $vec $acts $($t)*);
+    };
+
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => $($rs:ident)* ; $($t:tt)*) => {
+        $vec.push($crate::cfg::Production{left: String::from(stringify!($v)), right: vec![$($crate::to_symbol!(@ $terms $rs)),*], action: None});
+        $crate::productions!(@ $terms $vec $acts $($t)*);
+    };
 
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => $($rs:ident)* {$($f:tt)*} ; $($t:tt)*) => {
+        $crate::productions!(@action $vec $acts $v vec![$($crate::to_symbol!(@ $terms $rs)),*], $($f)*);
+        $crate::productions!(@ $terms $vec $acts $($t)*);
     };
 
-    (@ $terms:ident $vec:ident $v:ident => ; $($t:tt)*) => {
-        $vec.push(crate::cfg::Production{left: String::from(stringify!($v)), right: vec![crate::cfg::Symbol::Epsilon]});
-        productions!(@ $terms $vec $($t)*);
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => | $($t:tt)*) => {
+        $vec.push($crate::cfg::Production{left: String::from(stringify!($v)), right: vec![$crate::cfg::Symbol::Epsilon], action: None});
+        $crate::productions!(@ $terms $vec $acts $v => $($t)*);
     };
 
-    (@ $terms:ident $vec:ident $v:ident => $($rs:ident)* ; $($t:tt)*) => {
-        $vec.push(crate::cfg::Production{left: String::from(stringify!($v)), right: vec![$(to_symbol!(@ $terms $rs)),*]});
-        productions!(@ $terms $vec $($t)*);
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => {$($f:tt)*} | $($t:tt)*) => {
+        $crate::productions!(@action $vec $acts $v vec![$crate::cfg::Symbol::Epsilon], $($f)*);
+        $crate::productions!(@ $terms $vec $acts $v => $($t)*);
     };
 
-    (@ $terms:ident $vec:ident $v:ident => | $($t:tt)*) => {
-        $vec.push(crate::cfg::Production{left: String::from(stringify!($v)), right: vec![crate::cfg::Symbol::Epsilon]});
-        productions!(@ $terms $vec $v => $($t)*);
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => $($rs:ident)* | $($t:tt)*) => {
+        $vec.push($crate::cfg::Production{left: String::from(stringify!($v)), right: vec![$($crate::to_symbol!(@ $terms $rs)),*], action: None});
+        $crate::productions!(@ $terms $vec $acts $v => $($t)*);
     };
 
-    (@ $terms:ident $vec:ident $v:ident => $($rs:ident)* | $($t:tt)*) => {
-        $vec.push(crate::cfg::Production{left: String::from(stringify!($v)), right: vec![$(to_symbol!(@ $terms $rs)),*]});
-        productions!(@ $terms $vec $v => $($t)*);
+    (@ $terms:ident $vec:ident $acts:ident $v:ident => $($rs:ident)* {$($f:tt)*} | $($t:tt)*) => {
+        $crate::productions!(@action $vec $acts $v vec![$($crate::to_symbol!(@ $terms $rs)),*], $($f)*);
+        $crate::productions!(@ $terms $vec $acts $v => $($t)*);
+    };
+
+    (@action $vec:ident $acts:ident $v:ident $right:expr, $($f:tt)*) => {
+        $vec.push($crate::cfg::Production{left: String::from(stringify!($v)), right: $right, action: Some($acts.len())});
+        $acts.push($crate::cfg::action($($f)*));
     };
 
     (@ $terms:ident $($t:tt)*) => {
         {
             let mut vec = Vec::new();
-            productions!(@ $terms vec $($t)*);
-            vec
+            #[allow(unused_mut)]
+            let mut acts = Vec::new();
+            $crate::productions!(@ $terms vec acts $($t)*);
+            (vec, acts)
         }
     };
 }
 
 #[macro_export]
 macro_rules! build_terms {
-    (@next $map:ident) => {
+    (@begin $($k:ident = $v:expr),* $(,)?) => {
+        vec![$((std::mem::discriminant(&$v), String::from(stringify!($k)))),*]
     };
+}
 
-    (@next $map:ident $k:ident = $v:expr) => {
-        $map.insert(std::mem::discriminant(&$v), String::from(stringify!($k)));
+#[macro_export]
+macro_rules! assoc {
+    (left) => {
+        $crate::cfg::Assoc::Left
     };
-
-    (@next $map:ident $k:ident = $v:expr, $($rs:tt)*) => {
-        $map.insert(std::mem::discriminant(&$v), String::from(stringify!($k)));
-        build_terms!(@next $map $($rs)*);
+    (right) => {
+        $crate::cfg::Assoc::Right
+    };
+    (nonassoc) => {
+        $crate::cfg::Assoc::Nonassoc
     };
-
-    (@begin $($t:tt)*) => {
-        {
-            let mut map = std::collections::HashMap::new();
-            build_terms!(@next map $($t)*);
-            map
-        }
-    }
 }
 
 #[macro_export]
 macro_rules! context_free_grammar {
     (terminals: {$($t:tt)*} rules: {$($r:tt)*} start: $s:ident) => {
+        $crate::context_free_grammar!(terminals: {$($t)*} precedence: {} value: {()} rules: {$($r)*} start: $s)
+    };
+
+    (terminals: {$($t:tt)*} precedence: {$($p:tt)*} rules: {$($r:tt)*} start: $s:ident) => {
+        $crate::context_free_grammar!(terminals: {$($t)*} precedence: {$($p)*} value: {()} rules: {$($r)*} start: $s)
+    };
+
+    (terminals: {$($t:tt)*} value: {$v:ty} rules: {$($r:tt)*} start: $s:ident) => {
+        $crate::context_free_grammar!(terminals: {$($t)*} precedence: {} value: {$v} rules: {$($r)*} start: $s)
+    };
+
+    (terminals: {$($t:tt)*} precedence: {$($a:ident $($pt:ident)*;)*} value: {$v:ty} rules: {$($r:tt)*} start: $s:ident) => {
         {
-            let dis2term = build_terms!(@begin $($t)*);
-            let terms = dis2term.values().map(|x| x.clone()).collect::<Vec<String>>();
+            let terms_in_order = $crate::build_terms!(@begin $($t)*);
+            let terms = terms_in_order.iter().map(|x| x.1.clone()).collect::<Vec<String>>();
+            let dis2term = terms_in_order.into_iter().collect::<std::collections::HashMap<_, _>>();
             let start = stringify!($s);
-            let p = productions!(@ terms $($r)*);
-            crate::cfg::CFG {
+            let (p, actions) = $crate::productions!(@ terms $($r)*);
+            #[allow(unused_mut)]
+            let mut cfg: $crate::cfg::CFG<_, $v> = $crate::cfg::CFG {
                 discriminant2terminal: dis2term,
                 terminals: terms,
                 rules: p,
-                start: start.into()
-            }
+                start: start.into(),
+                precedence: std::collections::HashMap::new(),
+                actions,
+            };
+            $(cfg.add_precedence($crate::assoc!($a), &[$(stringify!($pt)),*]).unwrap();)*
+            cfg
         }
     };
 }
 
+/// The output of the top-down parsers, read back by `build_tree`. Every
+/// node is followed by its children: a matched terminal by `Val` and `End`,
+/// an expanded variable by `Rule`, its right side and `End`, and anything
+/// left unparsed by a lone `End`.
+pub(crate) enum TempNode<'a, T: 'a> {
+    Term(String),
+    Var(String),
+    /// The production a variable was expanded with.
+    Rule(usize),
+    /// The token at an index of the input.
+    Val(usize, &'a T),
+    Eps,
+    End,
+}
+
+impl<T> Clone for TempNode<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            TempNode::Term(t) => TempNode::Term(t.clone()),
+            TempNode::Var(v) => TempNode::Var(v.clone()),
+            TempNode::Rule(p) => TempNode::Rule(*p),
+            TempNode::Val(i, v) => TempNode::Val(*i, v),
+            TempNode::Eps => TempNode::Eps,
+            TempNode::End => TempNode::End,
+        }
+    }
+}
+
+/// Reads the children of one node: a leaf for a terminal, otherwise a node
+/// without a name holding the production and the subtrees.
+fn build_tree<'a, T>(iter: &mut std::vec::IntoIter<TempNode<'a, T>>) -> Tree<'a, T> {
+    let mut production = None;
+    let mut nodes = Vec::new();
+    loop {
+        match iter.next().unwrap() {
+            TempNode::End => break,
+            TempNode::Rule(p) => production = Some(p),
+            TempNode::Eps => nodes.push(Tree::Epsilon),
+            TempNode::Term(name) | TempNode::Var(name) => nodes.push(Tree::Node {
+                name,
+                production: None,
+                nodes: Vec::new(),
+            }),
+            TempNode::Val(index, token) => {
+                assert!(matches!(iter.next(), Some(TempNode::End)));
+                return Tree::Leaf { index, token };
+            }
+        }
+    }
+    for node in nodes.iter_mut() {
+        if let Tree::Node { .. } = node {
+            match build_tree(iter) {
+                leaf @ Tree::Leaf { .. } => *node = leaf,
+                Tree::Node {
+                    production: p,
+                    nodes: ns,
+                    ..
+                } => {
+                    if let Tree::Node {
+                        production, nodes, ..
+                    } = node
+                    {
+                        *production = p;
+                        *nodes = ns;
+                    }
+                }
+                Tree::Epsilon => unreachable!(),
+            }
+        }
+    }
+    Tree::Node {
+        name: "".into(),
+        production,
+        nodes,
+    }
+}
+
 fn normalize_rules(start: &str, rules: Vec<Production>) -> Vec<Production> {
     let mut rules_group = HashMap::new();
     for rule in rules {
@@ -186,7 +500,7 @@ fn remove_direct_left_recursion(
     let new_var = var.to_string() + NEW_VAR_SUFFIX;
     for prod in prods {
         if prod.starts_with(&[Symbol::Variable(var.into())]) {
-            let mut new_prod = (&prod[1..]).to_vec();
+            let mut new_prod = prod[1..].to_vec();
             new_prod.push(Symbol::Variable(new_var.clone()));
             newvar_rules.push(new_prod);
             need_newvar = true;
@@ -204,14 +518,110 @@ fn remove_direct_left_recursion(
     out.insert(var.to_string(), new_rules);
 }
 
+/// The first variable that derives a sentential form starting with itself,
+/// nullable prefixes skipped.
+fn find_left_recursion(groups: &[(String, Vec<Vec<Symbol>>)]) -> Option<String> {
+    let mut nullable: HashSet<&str> = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (var, prods) in groups {
+            if !nullable.contains(var.as_str())
+                && prods.iter().any(|p| {
+                    p.iter().all(|s| match s {
+                        Symbol::Epsilon => true,
+                        Symbol::Variable(v) => nullable.contains(v.as_str()),
+                        _ => false,
+                    })
+                })
+            {
+                nullable.insert(var);
+                changed = true;
+            }
+        }
+    }
+    let mut left: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (var, prods) in groups {
+        for prod in prods {
+            for sym in prod {
+                match sym {
+                    Symbol::Epsilon => continue,
+                    Symbol::Variable(v) => {
+                        left.entry(var).or_default().push(v);
+                        if nullable.contains(v.as_str()) {
+                            continue;
+                        }
+                    }
+                    _ => {}
+                }
+                break;
+            }
+        }
+    }
+    groups.iter().map(|g| g.0.as_str()).find_map(|var| {
+        let mut reached = HashSet::new();
+        let mut stack = vec![var];
+        while let Some(v) = stack.pop() {
+            for w in left.get(v).into_iter().flatten() {
+                if *w == var {
+                    return Some(var.to_string());
+                }
+                if reached.insert(*w) {
+                    stack.push(w);
+                }
+            }
+        }
+        None
+    })
+}
+
+/// Gives the productions of a rewrite the semantic actions of the old ones
+/// with the same sides. Fails if an action is lost, a production with one
+/// not surviving the rewrite while its variable does.
+fn carry_actions(old: &[Production], new: &mut [Production]) -> Result<(), RewriteError> {
+    for p in new.iter_mut() {
+        p.action = old
+            .iter()
+            .filter(|q| q.left == p.left && q.right == p.right)
+            .find_map(|q| q.action);
+    }
+    for q in old.iter().filter(|q| q.action.is_some()) {
+        if !new.contains(q) && new.iter().any(|p| p.left == q.left) {
+            return Err(RewriteError::Action(q.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn new_var_name(var: &str, taken: &[String]) -> String {
+    let mut new_var = var.to_string() + NEW_VAR_SUFFIX;
+    while taken.contains(&new_var) {
+        new_var += NEW_VAR_SUFFIX;
+    }
+    new_var
+}
+
+fn substitute(prods: &[Vec<Symbol>], var: &str, var_prods: &[Vec<Symbol>]) -> Vec<Vec<Symbol>> {
+    let mut new_prods = Vec::new();
+    for prod in apply_rules(prods, var, var_prods) {
+        let mut prod: Vec<_> = prod.into_iter().filter(|s| *s != Symbol::Epsilon).collect();
+        if prod.is_empty() {
+            prod.push(Symbol::Epsilon);
+        }
+        if !new_prods.contains(&prod) {
+            new_prods.push(prod);
+        }
+    }
+    new_prods
+}
+
 fn clear_unreached(start: &str, rules: HashMap<String, Vec<Vec<Symbol>>>) -> Vec<Production> {
     let mut stack = Vec::new();
     let mut reached = HashSet::new();
     let mut new_rules = Vec::new();
     stack.push(start.to_string());
     reached.insert(start.to_string());
-    while !stack.is_empty() {
-        let var = stack.pop().unwrap();
+    while let Some(var) = stack.pop() {
         if let Some(rs) = rules.get(&var) {
             for rule in rs {
                 for sym in rule {
@@ -225,6 +635,7 @@ fn clear_unreached(start: &str, rules: HashMap<String, Vec<Vec<Symbol>>>) -> Vec
                 new_rules.push(Production {
                     left: var.clone(),
                     right: rule.to_owned(),
+                    action: None,
                 })
             }
         }
@@ -232,92 +643,299 @@ fn clear_unreached(start: &str, rules: HashMap<String, Vec<Vec<Symbol>>>) -> Vec
     new_rules
 }
 
-impl<T> CFG<T> {
-    pub fn left_recursion_eliminate_unstable(&mut self) {
-        self.rules = normalize_rules(&self.start, std::mem::take(&mut self.rules));
+impl<T, V> CFG<T, V> {
+    pub fn left_recursion_eliminate_unstable(&mut self) -> Result<(), RewriteError> {
+        let mut rules = normalize_rules(&self.start, self.rules.clone());
+        carry_actions(&self.rules, &mut rules)?;
+        self.rules = rules;
+        Ok(())
     }
 
-    fn get_first(&self, x: &Symbol) -> HashSet<Symbol> {
-        let mut first = HashSet::new();
-        if let Symbol::Terminal(_) | Symbol::Epsilon = x {
-            first.insert(x.clone());
-        } else if let Symbol::Variable(v) = x {
-            for p in self.rules.iter().filter(|r| r.left == *v) {
-                let mut all_espilon = true;
-                for s in p.right.iter() {
-                    match s {
-                        Symbol::Terminal(t) => {
-                            first.insert(Symbol::Terminal(t.clone()));
-                            all_espilon = false;
-                            break;
-                        }
-                        Symbol::Variable(_) | Symbol::Epsilon => {
-                            let mut has_espilon = false;
-                            let v_first = self.get_first(s);
-                            for f in v_first {
-                                if f != Symbol::Epsilon {
-                                    first.insert(f);
-                                } else {
-                                    has_espilon = true;
-                                }
-                            }
-                            if !has_espilon {
-                                all_espilon = false;
-                                break;
-                            }
+    /// Textbook elimination of left recursion: with the variables ordered as
+    /// declared, substitutes `A_j` into every `A_i => A_j γ` for `j < i`, then
+    /// removes the direct left recursion of `A_i`. Cycles like `A => A` are
+    /// dropped and ε is never kept inside a longer body. Left recursion hidden
+    /// behind a nullable variable that comes later in the order survives
+    /// this, in which case the rules are left untouched and an error returned,
+    /// as they are when a production with a semantic action is rewritten.
+    pub fn left_recursion_eliminate(&mut self) -> Result<(), RewriteError> {
+        let mut taken = self.terminals.clone();
+        let mut groups: Vec<(String, Vec<Vec<Symbol>>)> = self
+            .get_variables()
+            .into_iter()
+            .map(|v| {
+                let prods = self
+                    .rules
+                    .iter()
+                    .filter(|r| r.left == v)
+                    .map(|r| r.right.clone())
+                    .collect();
+                taken.push(v.clone());
+                (v, prods)
+            })
+            .collect();
+        let mut i = 0;
+        while i < groups.len() {
+            let var = groups[i].0.clone();
+            let self_var = Symbol::Variable(var.clone());
+            for j in 0..i {
+                groups[i].1 = substitute(&groups[i].1, &groups[j].0, &groups[j].1);
+            }
+            groups[i].1.retain(|p| *p != [self_var.clone()]);
+
+            let (alphas, betas): (Vec<_>, Vec<_>) = groups[i]
+                .1
+                .iter()
+                .cloned()
+                .partition(|p| p.starts_with(std::slice::from_ref(&self_var)));
+            if !alphas.is_empty() {
+                let new_var = new_var_name(&var, &taken);
+                taken.push(new_var.clone());
+                let new_sym = Symbol::Variable(new_var.clone());
+                let mut new_prods: Vec<_> = alphas
+                    .into_iter()
+                    .map(|mut p| {
+                        p.remove(0);
+                        p.push(new_sym.clone());
+                        p
+                    })
+                    .collect();
+                if !betas.is_empty() {
+                    new_prods.push(vec![Symbol::Epsilon]);
+                }
+                groups[i].1 = if betas.is_empty() {
+                    vec![vec![new_sym.clone()]]
+                } else {
+                    betas
+                        .into_iter()
+                        .map(|mut p| {
+                            p.retain(|s| *s != Symbol::Epsilon);
+                            p.push(new_sym.clone());
+                            p
+                        })
+                        .collect()
+                };
+                groups.insert(i + 1, (new_var, new_prods));
+                i += 1;
+            }
+            i += 1;
+        }
+        if let Some(variable) = find_left_recursion(&groups) {
+            return Err(RewriteError::HiddenLeftRecursion(variable));
+        }
+
+        let mut reached = vec![self.start.clone()];
+        let mut stack = vec![self.start.clone()];
+        while let Some(var) = stack.pop() {
+            for prod in groups
+                .iter()
+                .filter(|g| g.0 == var)
+                .flat_map(|g| g.1.iter())
+            {
+                for sym in prod {
+                    if let Symbol::Variable(v) = sym {
+                        if !reached.contains(v) {
+                            reached.push(v.clone());
+                            stack.push(v.clone());
                         }
                     }
                 }
-                if all_espilon {
-                    first.insert(Symbol::Epsilon);
-                }
             }
         }
-        first
+        let mut rules: Vec<_> = groups
+            .into_iter()
+            .filter(|g| reached.contains(&g.0))
+            .flat_map(|(left, prods)| {
+                prods.into_iter().map(move |right| Production {
+                    left: left.clone(),
+                    right,
+                    action: None,
+                })
+            })
+            .collect();
+        carry_actions(&self.rules, &mut rules)?;
+        self.rules = rules;
+        Ok(())
     }
 
-    pub fn get_firsts(&self) -> Sets {
-        let t = self
-            .rules
+    /// Declares `terminals` on a new level, binding tighter than every level
+    /// declared before, as `%left`, `%right` and `%nonassoc` do in yacc.
+    /// Nothing is declared if one of `terminals` is not in the grammar.
+    pub fn add_precedence(
+        &mut self,
+        assoc: Assoc,
+        terminals: &[&str],
+    ) -> Result<(), UnknownTerminal> {
+        if let Some(t) = terminals
             .iter()
-            .map(|r| r.left.clone())
-            .collect::<HashSet<String>>();
-        Sets(
-            t.into_iter()
-                .map(|s| (s.clone(), self.get_first(&Symbol::Variable(s))))
-                .collect(),
-        )
+            .find(|t| !self.terminals.iter().any(|x| x == *t))
+        {
+            return Err(UnknownTerminal(t.to_string()));
+        }
+        let level = self.precedence.values().map(|p| p.0 + 1).max().unwrap_or(0);
+        for t in terminals {
+            self.precedence.insert(t.to_string(), (level, assoc));
+        }
+        Ok(())
     }
 
-    fn get_string_first(&self, str: &[Symbol]) -> HashSet<Symbol> {
-        let mut first = HashSet::new();
-        let mut all_espilon = true;
-        for s in str {
-            let v_first = self.get_first(s);
-            let mut has_espilon = false;
-            for v in v_first {
-                if v != Symbol::Epsilon {
-                    first.insert(v);
-                } else {
-                    has_espilon = true;
+    /// The precedence of a production is that of its last terminal.
+    pub fn get_production_precedence(&self, p: &Production) -> Option<(usize, Assoc)> {
+        let last = p.right.iter().rev().find_map(|s| match s {
+            Symbol::Terminal(t) => Some(t),
+            _ => None,
+        })?;
+        self.precedence.get(last).copied()
+    }
+
+    pub fn get_variables(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        for r in self.rules.iter() {
+            if !vars.contains(&r.left) {
+                vars.push(r.left.clone());
+            }
+        }
+        vars
+    }
+
+    pub(crate) fn new_var(&self, var: &str) -> String {
+        let mut taken = self.terminals.clone();
+        for r in self.rules.iter() {
+            taken.push(r.left.clone());
+            for sym in r.right.iter() {
+                if let Symbol::Variable(v) = sym {
+                    taken.push(v.clone());
                 }
             }
-            if !has_espilon {
-                all_espilon = false;
-                break;
+        }
+        new_var_name(var, &taken)
+    }
+
+    fn left_factor_var(&mut self, var: &str) -> bool {
+        let prods: Vec<_> = (0..self.rules.len())
+            .filter(|i| self.rules[*i].left == var)
+            .collect();
+        let group = prods.iter().find_map(|i| {
+            let head = &self.rules[*i].right[0];
+            let group: Vec<_> = prods
+                .iter()
+                .copied()
+                .filter(|j| self.rules[*j].right[0] == *head)
+                .collect();
+            if *head != Symbol::Epsilon && group.len() > 1 {
+                Some(group)
+            } else {
+                None
+            }
+        });
+        let group = match group {
+            Some(group) => group,
+            None => return false,
+        };
+        let first = &self.rules[group[0]].right;
+        let prefix_len = (1..first.len())
+            .take_while(|n| {
+                group
+                    .iter()
+                    .all(|j| self.rules[*j].right.get(*n) == Some(&first[*n]))
+            })
+            .count()
+            + 1;
+        let mut suffixes: Vec<Vec<Symbol>> = Vec::new();
+        for j in group.iter() {
+            let suffix = match &self.rules[*j].right[prefix_len..] {
+                [] => vec![Symbol::Epsilon],
+                suffix => suffix.to_vec(),
+            };
+            if !suffixes.contains(&suffix) {
+                suffixes.push(suffix);
             }
         }
-        if all_espilon {
-            first.insert(Symbol::Epsilon);
+        // The alternatives of the group are all the same, keep one of them.
+        if suffixes.len() == 1 {
+            for j in group[1..].iter().rev() {
+                self.rules.remove(*j);
+            }
+            return true;
         }
-        first
+        let new_var = self.new_var(var);
+        let mut prefix = first[..prefix_len].to_vec();
+        prefix.push(Symbol::Variable(new_var.clone()));
+        let new_rules: Vec<_> = suffixes
+            .into_iter()
+            .map(|right| Production {
+                left: new_var.clone(),
+                right,
+                action: None,
+            })
+            .collect();
+        let last = *prods.last().unwrap();
+        self.rules.splice(last + 1..last + 1, new_rules);
+        self.rules[group[0]] = Production {
+            left: var.to_string(),
+            right: prefix,
+            action: None,
+        };
+        for j in group[1..].iter().rev() {
+            self.rules.remove(*j);
+        }
+        true
+    }
+
+    /// Extracts the longest common prefix of alternatives into a fresh
+    /// variable, `A => a b | a c` becomes `A => a A'` and `A' => b | c`,
+    /// until no two alternatives of a variable start with the same symbol.
+    /// Fails, leaving the rules untouched, if a production with a semantic
+    /// action would be factored.
+    pub fn left_factor(&mut self) -> Result<(), RewriteError> {
+        let old = self.rules.clone();
+        let mut changed = true;
+        while changed {
+            changed = self.get_variables().iter().any(|v| self.left_factor_var(v));
+        }
+        let carried = carry_actions(&old, &mut self.rules);
+        if carried.is_err() {
+            self.rules = old;
+        }
+        carried
+    }
+
+    /// Computes FIRST of every variable as a fixpoint over all productions,
+    /// so left-recursive grammars are fine. A set holds `Epsilon` when the
+    /// variable is nullable.
+    pub fn get_firsts(&self) -> Sets {
+        let mut firsts = Sets(
+            self.get_variables()
+                .into_iter()
+                .map(|v| (v, HashSet::new()))
+                .collect(),
+        );
+        loop {
+            let mut changed = false;
+            for p in self.rules.iter() {
+                let first = firsts.get_string_first(&p.right);
+                let set = firsts.0.get_mut(&p.left).unwrap();
+                for s in first {
+                    changed |= set.insert(s);
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        firsts
     }
 
     pub fn get_follows(&self) -> Sets {
+        self.get_follows_with(&self.get_firsts())
+    }
+
+    /// Same as `get_follows`, reusing FIRST sets already computed.
+    pub fn get_follows_with(&self, firsts: &Sets) -> Sets {
         let mut follows = HashMap::new();
         follows.insert(self.start.clone(), {
             let mut set = HashSet::new();
-            set.insert(Symbol::Epsilon);
+            set.insert(Symbol::EndOfInput);
             set
         });
         loop {
@@ -325,7 +943,7 @@ impl<T> CFG<T> {
             for p in self.rules.iter() {
                 for i in 1..=p.right.len() {
                     if let Symbol::Variable(v) = &p.right[i - 1] {
-                        let next_follow = self.get_string_first(&p.right[i..]);
+                        let next_follow = firsts.get_string_first(&p.right[i..]);
                         if next_follow.contains(&Symbol::Epsilon) {
                             let follow_a = follows
                                 .entry(p.left.clone())
@@ -352,147 +970,398 @@ impl<T> CFG<T> {
         Sets(follows)
     }
 
-    pub fn get_table(&self) -> Table {
-        let mut table = HashMap::new();
-        let follows = self.get_follows();
-        for p in self.rules.iter() {
-            let first = self.get_string_first(&p.right);
-            for s in first.iter() {
-                if let Symbol::Terminal(_) = s {
-                    if table.insert((p.left.clone(), s.clone()), p).is_some() {
-                        panic!("Not LL(1) Grammar");
-                    }
-                }
+    /// Every `(lookahead, production, from FOLLOW)` entry of the LL(1) table,
+    /// in the order the productions are given.
+    fn get_table_entries(&self) -> Vec<(Symbol, usize, bool)> {
+        let firsts = self.get_firsts();
+        let follows = self.get_follows_with(&firsts);
+        let mut entries = Vec::new();
+        for (i, p) in self.rules.iter().enumerate() {
+            let first = firsts.get_string_first(&p.right);
+            let mut first_terms: Vec<_> = first
+                .iter()
+                .filter(|s| matches!(s, Symbol::Terminal(_)))
+                .collect();
+            first_terms.sort();
+            for s in first_terms {
+                entries.push((s.clone(), i, false));
             }
             if first.contains(&Symbol::Epsilon) {
                 if let Some(follow_a) = follows.0.get(&p.left) {
+                    let mut follow_a: Vec<_> = follow_a.iter().collect();
+                    follow_a.sort();
                     for s in follow_a {
                         match s {
-                            Symbol::Terminal(_) | Symbol::Epsilon => {
-                                if table.insert((p.left.clone(), s.clone()), p).is_some() {
-                                    panic!("Not LL(1) Grammar");
-                                }
+                            Symbol::Terminal(_) | Symbol::EndOfInput => {
+                                entries.push((s.clone(), i, true))
                             }
-                            Symbol::Variable(_) => unreachable!(),
+                            Symbol::Variable(_) | Symbol::Epsilon => unreachable!(),
                         }
                     }
                 }
             }
         }
-        Table(table)
+        entries
     }
 
-    pub fn parse<'a>(&self, tokens: &'a [T]) -> Result<Tree<'a, T>, String> {
-        enum TempNode<'a, T: 'a> {
-            Term(String),
-            Var(String),
-            Val(&'a T),
-            Eps,
-            End,
-        }
-
-        fn build_tree<'a, T>(iter: &mut std::vec::IntoIter<TempNode<'a, T>>) -> Tree<'a, T> {
-            let mut nodes = Vec::new();
-            loop {
-                let next = iter.next().unwrap();
-                nodes.push(match next {
-                    TempNode::End => break,
-                    TempNode::Eps => {
-                        assert!(matches!(iter.next(), Some(TempNode::End)));
-                        return Tree::Epslion;
-                    }
-                    TempNode::Term(t) => Tree::Node {
-                        name: t,
-                        nodes: Vec::new(),
-                    },
-                    TempNode::Var(v) => Tree::Node {
-                        name: v,
-                        nodes: Vec::new(),
+    pub fn get_table(&self) -> Result<Table<'_>, Vec<Conflict>> {
+        let entries = self.get_table_entries();
+        let mut table: HashMap<(String, Symbol), usize> = HashMap::new();
+        let mut from_follow = HashSet::new();
+        let mut conflicts = Vec::new();
+        for (s, p, follow) in entries {
+            let key = (self.rules[p].left.clone(), s);
+            if let Some(&q) = table.get(&key) {
+                // A nullable production reaches the lookaheads it shares
+                // with FOLLOW twice, which is no conflict.
+                if q == p {
+                    continue;
+                }
+                conflicts.push(Conflict {
+                    kind: if follow || from_follow.contains(&key) {
+                        ConflictKind::FirstFollow
+                    } else {
+                        ConflictKind::FirstFirst
                     },
-                    TempNode::Val(v) => {
-                        assert!(matches!(iter.next(), Some(TempNode::End)));
-                        return Tree::Leaf(v);
-                    }
-                })
-            }
-            for node in nodes.iter_mut() {
-                if let Tree::Node { name: _, nodes: ns } = node {
-                    let t = build_tree(iter);
-                    match t {
-                        Tree::Leaf(_) => *node = t,
-                        Tree::Epslion => *ns = vec![t],
-                        Tree::Node {
-                            name: _,
-                            nodes: tns,
-                        } => *ns = tns,
-                    }
+                    variable: key.0,
+                    lookahead: key.1,
+                    productions: (self.rules[q].clone(), self.rules[p].clone()),
+                });
+            } else {
+                if follow {
+                    from_follow.insert(key.clone());
                 }
+                table.insert(key, p);
             }
-            Tree::Node {
-                name: "".into(),
-                nodes,
+        }
+        if conflicts.is_empty() {
+            Ok(Table {
+                cells: table,
+                rules: &self.rules,
+                variables: self.get_variables(),
+                terminals: self.get_table_terminals(),
+            })
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    /// The LL(1) table keeping every production of a cell, for grammars that
+    /// are not LL(1).
+    pub fn get_multi_table(&self) -> MultiTable<'_> {
+        let mut cells: HashMap<(String, Symbol), Vec<usize>> = HashMap::new();
+        for (s, p, _) in self.get_table_entries() {
+            let cell = cells.entry((self.rules[p].left.clone(), s)).or_default();
+            if !cell.contains(&p) {
+                cell.push(p);
             }
         }
+        MultiTable {
+            cells,
+            rules: &self.rules,
+            variables: self.get_variables(),
+            terminals: self.get_table_terminals(),
+        }
+    }
 
-        let table = self.get_table().0;
-        let mut stack = vec![Symbol::Variable(self.start.clone())];
-        let mut iter = tokens.iter().peekable();
+    fn get_table_terminals(&self) -> Vec<Symbol> {
+        self.terminals
+            .iter()
+            .map(|t| Symbol::Terminal(t.clone()))
+            .chain(std::iter::once(Symbol::EndOfInput))
+            .collect()
+    }
+
+    /// The terminal of the token at `index`, `EndOfInput` past the end and
+    /// `None` for a token not bound to any terminal.
+    pub(crate) fn get_terminal(&self, tokens: &[T], index: usize) -> Option<Symbol> {
+        match tokens.get(index) {
+            #[allow(enum_intrinsics_non_enums)]
+            Some(v) => self
+                .discriminant2terminal
+                .get(&discriminant(v))
+                .map(|t| Symbol::Terminal(t.clone())),
+            None => Some(Symbol::EndOfInput),
+        }
+    }
+
+    /// The predictive parsing loop. Without `follows` it stops at the first
+    /// error, otherwise it recovers in panic mode, using FOLLOW of the
+    /// variable on top of the stack as the synchronizing set.
+    /// When `trace` is given, a step is recorded before every action.
+    fn run_parse<'a, 'g>(
+        &self,
+        table: &Table<'g>,
+        follows: Option<&Sets>,
+        tokens: &'a [T],
+        mut trace: Option<&mut Vec<ParseStep<'g>>>,
+    ) -> (Vec<TempNode<'a, T>>, Vec<ParseError>) {
+        let mut stack = vec![Symbol::EndOfInput, Symbol::Variable(self.start.clone())];
+        let mut pos = 0;
         let mut out: Vec<TempNode<'a, T>> = Vec::new();
-        while !stack.is_empty() {
-            let x = stack.pop().unwrap();
-            let a = match iter.peek() {
-                #[allow(clippy::mem_discriminant_non_enum)]
-                Some(v) => Symbol::Terminal(
-                    self.discriminant2terminal
-                        .get(&discriminant(v))
-                        .unwrap()
-                        .clone(),
-                ),
-                None => Symbol::Epsilon,
+        let mut errors = Vec::new();
+        // Set while tokens are discarded to reach a synchronizing token, the
+        // error that started it standing for the whole run.
+        let mut skipping = false;
+        // Only the first error at a token is reported, the others follow from it.
+        let report = |errors: &mut Vec<ParseError>, error: ParseError| {
+            if errors.last().map(|e: &ParseError| e.index()) != Some(error.index()) {
+                errors.push(error);
+            }
+        };
+        while let Some(x) = stack.pop() {
+            if let Some(trace) = trace.as_mut() {
+                let mut step_stack = stack.clone();
+                step_stack.push(x.clone());
+                trace.push(ParseStep {
+                    stack: step_stack,
+                    input: (pos..=tokens.len())
+                        .filter_map(|i| self.get_terminal(tokens, i))
+                        .collect(),
+                    action: ParseAction::Accept,
+                });
+            }
+            let mut act = |action: ParseAction<'g>| {
+                if let Some(step) = trace.as_mut().and_then(|t| t.last_mut()) {
+                    step.action = action;
+                }
+            };
+            let a = match self.get_terminal(tokens, pos) {
+                Some(a) => a,
+                None => {
+                    act(ParseAction::Error(ParseError::UnknownToken { index: pos }));
+                    report(&mut errors, ParseError::UnknownToken { index: pos });
+                    if follows.is_none() {
+                        break;
+                    }
+                    stack.push(x);
+                    pos += 1;
+                    continue;
+                }
             };
             match x {
-                Symbol::Terminal(_) => {
+                Symbol::Terminal(_) | Symbol::EndOfInput => {
                     if x == a {
-                        let val = iter.next().unwrap();
-                        out.push(TempNode::Val(val));
-                        out.push(TempNode::End);
+                        if let Symbol::Terminal(_) = x {
+                            act(ParseAction::Match(x));
+                            out.push(TempNode::Val(pos, &tokens[pos]));
+                            out.push(TempNode::End);
+                            pos += 1;
+                            skipping = false;
+                        }
                     } else {
-                        return Err(format!("Here should be {:?} be found {:?}", x, a));
+                        let error = ParseError::UnexpectedToken {
+                            index: pos,
+                            found: a,
+                            expected: vec![x.clone()],
+                        };
+                        act(ParseAction::Error(error.clone()));
+                        report(&mut errors, error);
+                        if follows.is_none() {
+                            break;
+                        }
+                        if let Symbol::Terminal(_) = x {
+                            out.push(TempNode::End);
+                        }
                     }
                 }
                 Symbol::Variable(v) => {
                     let va = (v, a);
-                    if let Some(rule) = table.get(&va) {
+                    if let Some(&p) = table.cells.get(&va) {
+                        let rule = &table.rules[p];
+                        act(ParseAction::Expand(rule));
+                        out.push(TempNode::Rule(p));
                         for sym in rule.right.iter() {
                             out.push(match sym {
                                 Symbol::Epsilon => TempNode::Eps,
                                 Symbol::Terminal(t) => TempNode::Term(t.clone()),
                                 Symbol::Variable(v) => TempNode::Var(v.clone()),
+                                Symbol::EndOfInput => unreachable!(),
                             })
                         }
                         out.push(TempNode::End);
                         for sym in rule.right.iter().rev() {
-                            stack.push(sym.clone());
+                            if *sym != Symbol::Epsilon {
+                                stack.push(sym.clone());
+                            }
                         }
+                        skipping = false;
                     } else {
-                        return Err(format!("Not found rule for {:?}", va));
+                        let (v, a) = va;
+                        let error = ParseError::UnexpectedToken {
+                            index: pos,
+                            found: a.clone(),
+                            expected: table.get_expected(&v),
+                        };
+                        act(ParseAction::Error(error.clone()));
+                        if !skipping {
+                            report(&mut errors, error);
+                        }
+                        let follows = match follows {
+                            Some(follows) => follows,
+                            None => break,
+                        };
+                        // Popping the last variable would leave the rest of the
+                        // input unparsed, so it only skips tokens.
+                        let sync = a == Symbol::EndOfInput
+                            || (stack.len() > 1
+                                && follows.0.get(&v).is_some_and(|f| f.contains(&a)));
+                        if sync {
+                            out.push(TempNode::End);
+                        } else {
+                            stack.push(Symbol::Variable(v));
+                            pos += 1;
+                            skipping = true;
+                        }
                     }
                 }
-                Symbol::Epsilon => {
-                    out.push(TempNode::Eps);
-                    out.push(TempNode::End);
-                }
+                Symbol::Epsilon => {}
             }
         }
-        let out_tree = build_tree(&mut out.into_iter());
-        let tree = Tree::Node {
-            name: self.start.clone(),
-            nodes: match out_tree {
-                Tree::Epslion | Tree::Leaf(_) => vec![out_tree],
-                Tree::Node { name: _, nodes } => nodes,
-            },
+        (out, errors)
+    }
+
+    pub fn parse<'a>(&self, tokens: &'a [T]) -> Result<Tree<'a, T>, ParseError> {
+        let table = self.get_table().map_err(ParseError::NotLL1)?;
+        let (out, errors) = self.run_parse(&table, None, tokens, None);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(self.build_tree(out)),
+        }
+    }
+
+    /// Same as `parse`, also returning the steps taken up to the end of the
+    /// input or the first error. The trace is empty if the grammar is not
+    /// LL(1).
+    pub fn parse_trace<'a>(
+        &self,
+        tokens: &'a [T],
+    ) -> (Result<Tree<'a, T>, ParseError>, ParseTrace<'_>) {
+        let table = match self.get_table() {
+            Ok(table) => table,
+            Err(conflicts) => return (Err(ParseError::NotLL1(conflicts)), ParseTrace(Vec::new())),
+        };
+        let mut steps = Vec::new();
+        let (out, errors) = self.run_parse(&table, None, tokens, Some(&mut steps));
+        let result = match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(self.build_tree(out)),
+        };
+        (result, ParseTrace(steps))
+    }
+
+    /// Parses the whole input even when it has syntax errors and returns
+    /// every error found together with the tree built around them. Missing
+    /// terminals and variables given up on are left without children.
+    pub fn parse_with_recovery<'a>(
+        &self,
+        tokens: &'a [T],
+    ) -> Result<(Tree<'a, T>, Vec<ParseError>), Vec<Conflict>> {
+        let table = self.get_table()?;
+        let follows = self.get_follows();
+        let (out, errors) = self.run_parse(&table, Some(&follows), tokens, None);
+        Ok((self.build_tree(out), errors))
+    }
+
+    /// Same as `parse`, returning the value computed by the semantic actions.
+    pub fn evaluate(&self, tokens: &[T]) -> Result<V, ParseError> {
+        let tree = self.parse(tokens)?;
+        Ok(self.eval_tree(&tree))
+    }
+
+    /// The value of a tree returned by any of the parsers. Each variable gets
+    /// the value its production's action computes from the children, or the
+    /// value of the first child if the production has no action.
+    ///
+    /// Panics if a production without an action does not start with a
+    /// variable, or if the tree holds errors.
+    pub fn eval_tree(&self, tree: &Tree<'_, T>) -> V {
+        let (production, nodes) = match tree {
+            Tree::Node {
+                production: Some(p),
+                nodes,
+                ..
+            } => (&self.rules[*p], nodes),
+            _ => panic!("expected an expanded variable"),
         };
-        Ok(tree)
+        let mut args = Vec::new();
+        for node in nodes.iter() {
+            match node {
+                Tree::Epsilon => {}
+                Tree::Leaf { token, .. } => args.push(Arg::Token(*token)),
+                Tree::Node { .. } => args.push(Arg::Value(self.eval_tree(node))),
+            }
+        }
+        match production.action {
+            Some(a) => (self.actions[a].0)(args),
+            None => match args.into_iter().next() {
+                Some(Arg::Value(v)) => v,
+                _ => panic!("no semantic action for {}", production),
+            },
+        }
+    }
+
+    pub(crate) fn build_tree<'a>(&self, out: Vec<TempNode<'a, T>>) -> Tree<'a, T> {
+        build_root(&self.start, out)
+    }
+}
+
+/// The tree of `start` from the nodes output by a parse.
+pub(crate) fn build_root<'a, T>(start: &str, out: Vec<TempNode<'a, T>>) -> Tree<'a, T> {
+    let (production, nodes) = match build_tree(&mut out.into_iter()) {
+        Tree::Node {
+            production, nodes, ..
+        } => (production, nodes),
+        out_tree => (None, vec![out_tree]),
+    };
+    Tree::Node {
+        name: start.to_string(),
+        production,
+        nodes,
+    }
+}
+
+impl Sets {
+    pub fn get(&self, var: &str) -> Option<&HashSet<Symbol>> {
+        self.0.get(var)
+    }
+
+    /// FIRST of a string of symbols, with `self` being the FIRST sets of the
+    /// variables. Contains `Epsilon` when every symbol is nullable.
+    pub fn get_string_first(&self, str: &[Symbol]) -> HashSet<Symbol> {
+        let mut first = HashSet::new();
+        for s in str {
+            match s {
+                Symbol::Terminal(_) | Symbol::EndOfInput => {
+                    first.insert(s.clone());
+                    return first;
+                }
+                Symbol::Epsilon => {}
+                Symbol::Variable(v) => {
+                    let v_first = match self.0.get(v) {
+                        Some(f) => f,
+                        None => return first,
+                    };
+                    first.extend(v_first.iter().filter(|x| **x != Symbol::Epsilon).cloned());
+                    if !v_first.contains(&Symbol::Epsilon) {
+                        return first;
+                    }
+                }
+            }
+        }
+        first.insert(Symbol::Epsilon);
+        first
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symbol::Terminal(t) => write!(f, "{}", t),
+            Symbol::Variable(v) => write!(f, "{}", v),
+            Symbol::Epsilon => write!(f, "{}", EPSILON),
+            Symbol::EndOfInput => write!(f, "{}", END_OF_INPUT),
+        }
     }
 }
 
@@ -501,11 +1370,7 @@ impl Display for Production {
         let right = self
             .right
             .iter()
-            .map(|x| match x {
-                Symbol::Terminal(t) => t.to_string(),
-                Symbol::Variable(v) => v.clone(),
-                Symbol::Epsilon => EPSILON.into(),
-            })
+            .map(|x| x.to_string())
             .collect::<Vec<_>>()
             .join(" ");
         write!(f, "{} => {}", self.left, right)
@@ -520,11 +1385,7 @@ impl Display for Sets {
                 "{}: {{ {} }}",
                 key,
                 val.iter()
-                    .map(|x| match x {
-                        Symbol::Terminal(t) => t.to_string(),
-                        Symbol::Variable(v) => v.clone(),
-                        Symbol::Epsilon => EPSILON.into(),
-                    })
+                    .map(|x| x.to_string())
                     .collect::<Vec<_>>()
                     .join(", ")
             )?;
@@ -533,21 +1394,648 @@ impl Display for Sets {
     }
 }
 
+impl MultiTable<'_> {
+    /// The terminals with at least one entry in the row of `var`.
+    pub fn get_expected(&self, var: &str) -> Vec<Symbol> {
+        self.terminals
+            .iter()
+            .filter(|t| self.cells.contains_key(&(var.to_string(), (*t).clone())))
+            .cloned()
+            .collect()
+    }
+
+    /// The cells with more than one production.
+    pub fn get_conflicts(&self) -> Vec<(&String, &Symbol)> {
+        let mut conflicts: Vec<_> = self
+            .cells
+            .iter()
+            .filter(|(_, ps)| ps.len() > 1)
+            .map(|((v, a), _)| (v, a))
+            .collect();
+        conflicts.sort();
+        conflicts
+    }
+}
+
+impl Table<'_> {
+    /// The terminals with an entry in the row of `var`.
+    pub fn get_expected(&self, var: &str) -> Vec<Symbol> {
+        self.terminals
+            .iter()
+            .filter(|t| self.cells.contains_key(&(var.to_string(), (*t).clone())))
+            .cloned()
+            .collect()
+    }
+
+    /// The table as rows of cells, headed by the terminals.
+    pub fn get_grid(&self) -> Vec<Vec<String>> {
+        let mut grid = vec![std::iter::once(String::new())
+            .chain(self.terminals.iter().map(|t| t.to_string()))
+            .collect::<Vec<_>>()];
+        for var in self.variables.iter() {
+            grid.push(
+                std::iter::once(var.clone())
+                    .chain(self.terminals.iter().map(|t| {
+                        match self.cells.get(&(var.clone(), t.clone())) {
+                            Some(p) => self.rules[*p].to_string(),
+                            None => String::new(),
+                        }
+                    }))
+                    .collect(),
+            );
+        }
+        grid
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let grid = self.get_grid();
+        let mut out = String::new();
+        for (i, row) in grid.iter().enumerate() {
+            let cells: Vec<_> = row.iter().map(|c| c.replace('|', "\\|")).collect();
+            out += &format!("| {} |\n", cells.join(" | "));
+            if i == 0 {
+                out += &format!("|{}\n", "---|".repeat(row.len()));
+            }
+        }
+        out
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in self.get_grid() {
+            let cells: Vec<_> = row
+                .iter()
+                .map(|c| {
+                    if c.contains([',', '"', '\n']) {
+                        format!("\"{}\"", c.replace('"', "\"\""))
+                    } else {
+                        c.clone()
+                    }
+                })
+                .collect();
+            out += &cells.join(",");
+            out += "\n";
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        fn escape(s: &str) -> String {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        }
+        let mut out = String::from("<table>\n");
+        for (i, row) in self.get_grid().iter().enumerate() {
+            let tag = if i == 0 { "th" } else { "td" };
+            out += "  <tr>";
+            for (j, cell) in row.iter().enumerate() {
+                let tag = if j == 0 { "th" } else { tag };
+                out += &format!("<{}>{}</{}>", tag, escape(cell), tag);
+            }
+            out += "</tr>\n";
+        }
+        out += "</table>\n";
+        out
+    }
+}
+
+/// Writes the rows with every column padded to its widest cell.
+pub(crate) fn write_grid(f: &mut Formatter<'_>, grid: &[Vec<String>]) -> std::fmt::Result {
+    let widths: Vec<_> = (0..grid[0].len())
+        .map(|j| grid.iter().map(|r| r[j].chars().count()).max().unwrap())
+        .collect();
+    for row in grid.iter() {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(c, w)| format!("{}{}", c, " ".repeat(w - c.chars().count())))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        writeln!(f, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
 impl Display for Table<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for ((term, var), prod) in self.0.iter() {
-            writeln!(
+        write_grid(f, &self.get_grid())
+    }
+}
+
+impl Display for ParseAction<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseAction::Match(t) => write!(f, "match {}", t),
+            ParseAction::Expand(p) => write!(f, "output {}", p),
+            ParseAction::Accept => write!(f, "accept"),
+            ParseAction::Error(e) => write!(f, "error: {}", e),
+        }
+    }
+}
+
+impl Display for ParseTrace<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let join = |syms: &[Symbol]| {
+            syms.iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mut grid = vec![vec!["Stack".into(), "Input".into(), "Action".into()]];
+        for step in self.0.iter() {
+            grid.push(vec![
+                join(&step.stack),
+                join(&step.input),
+                step.action.to_string(),
+            ]);
+        }
+        write_grid(f, &grid)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedToken {
+                index,
+                found,
+                expected,
+            } => {
+                let expected = expected
+                    .iter()
+                    .map(|s| format!("`{}`", s))
+                    .collect::<Vec<_>>();
+                if expected.len() == 1 {
+                    write!(f, "expected {}", expected[0])?;
+                } else {
+                    write!(f, "expected one of {}", expected.join(", "))?;
+                }
+                write!(f, " but found `{}` at token {}", found, index)
+            }
+            ParseError::UnknownToken { index } => write!(f, "unknown token at {}", index),
+            ParseError::NotLL1(conflicts) => {
+                let conflicts = conflicts.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                write!(f, "not an LL(1) grammar: {}", conflicts.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Display for RewriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RewriteError::HiddenLeftRecursion(variable) => write!(
                 f,
-                "( {}, {} ): {}",
-                term,
-                match var {
-                    Symbol::Terminal(t) => t.to_string(),
-                    Symbol::Variable(v) => v.clone(),
-                    Symbol::Epsilon => EPSILON.into(),
-                },
-                prod
-            )?;
+                "left recursion of `{}` is hidden behind a nullable prefix",
+                variable
+            ),
+            RewriteError::Action(p) => {
+                write!(f, "cannot rewrite `{}`, which has a semantic action", p)
+            }
         }
-        Ok(())
     }
 }
+
+impl std::error::Error for RewriteError {}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} conflict at ( {}, {} ): {} | {}",
+            match self.kind {
+                ConflictKind::FirstFirst => "FIRST/FIRST",
+                ConflictKind::FirstFollow => "FIRST/FOLLOW",
+            },
+            self.variable,
+            self.lookahead,
+            self.productions.0,
+            self.productions.1
+        )
+    }
+}
+
+#[test]
+fn test_table_conflicts() {
+    let c: CFG<()> = CFG::from_bnf("S -> i E t S | i E t S e S | a\nE -> b").unwrap();
+    let conflicts = c.get_table().unwrap_err();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].kind, ConflictKind::FirstFirst);
+    assert_eq!(
+        conflicts[0].to_string(),
+        "FIRST/FIRST conflict at ( S, i ): S => i E t S | S => i E t S e S"
+    );
+
+    let c: CFG<()> = CFG::from_bnf("S -> A a | b A\nA -> a | ε").unwrap();
+    let conflicts = c.get_table().unwrap_err();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].kind, ConflictKind::FirstFollow);
+    assert_eq!(conflicts[0].lookahead, Symbol::Terminal("a".into()));
+    let error = c.parse(&[]).unwrap_err();
+    assert_eq!(error, ParseError::NotLL1(conflicts));
+    assert_eq!(error.index(), None);
+
+    // `A => B` is in the cell of `x` through both FIRST(B) and FOLLOW(A),
+    // only `B` has a conflict.
+    let c: CFG<()> = CFG::from_bnf("S -> A x\nA -> B\nB -> x | ε").unwrap();
+    let conflicts = c.get_table().unwrap_err();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(
+        conflicts[0].to_string(),
+        "FIRST/FOLLOW conflict at ( B, x ): B => x | B => #"
+    );
+
+    let c: CFG<()> = CFG::from_bnf("S -> a S | b").unwrap();
+    assert!(c.get_table().is_ok());
+}
+
+#[test]
+fn test_left_factor() {
+    let mut c: CFG<()> = CFG::from_bnf("S -> i E t S | i E t S e S | a\nE -> b\nS' -> c").unwrap();
+    c.left_factor().unwrap();
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec![
+            "S => i E t S S''",
+            "S => a",
+            "S'' => #",
+            "S'' => e S",
+            "E => b",
+            "S' => c",
+        ]
+    );
+
+    let mut c: CFG<()> = CFG::from_bnf("A -> a b c | a b d | a e | f").unwrap();
+    c.left_factor().unwrap();
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec![
+            "A => a A'",
+            "A => f",
+            "A' => b A''",
+            "A' => e",
+            "A'' => c",
+            "A'' => d",
+        ]
+    );
+    assert!(c.get_table().is_ok());
+
+    let mut c: CFG<()> = CFG::from_bnf("A -> a | a | a b | a b").unwrap();
+    c.left_factor().unwrap();
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec!["A => a A'", "A' => #", "A' => b"]
+    );
+
+    let mut c: CFG<()> = CFG::from_bnf("A -> a b | a b | c").unwrap();
+    c.left_factor().unwrap();
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec!["A => a b", "A => c"]
+    );
+}
+
+#[test]
+fn test_left_recursion_eliminate() {
+    let src = "E -> E a T | T\nT -> T m F | F\nF -> l E r | d";
+    let mut c: CFG<()> = CFG::from_bnf(src).unwrap();
+    c.left_recursion_eliminate().unwrap();
+    let rules: Vec<_> = c.rules.iter().map(|r| r.to_string()).collect();
+    assert_eq!(
+        rules,
+        vec![
+            "E => T E'",
+            "E' => a T E'",
+            "E' => #",
+            "T => F T'",
+            "T' => m F T'",
+            "T' => #",
+            "F => l E r",
+            "F => d",
+        ]
+    );
+    for _ in 0..10 {
+        let mut c: CFG<()> = CFG::from_bnf(src).unwrap();
+        c.left_recursion_eliminate().unwrap();
+        assert_eq!(
+            rules,
+            c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>()
+        );
+    }
+
+    let mut c: CFG<()> = CFG::from_bnf("S -> A a | b\nA -> A c | S d | ε | A").unwrap();
+    c.left_recursion_eliminate().unwrap();
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec![
+            "S => A a",
+            "S => b",
+            "A => b d A'",
+            "A => A'",
+            "A' => c A'",
+            "A' => a d A'",
+            "A' => #",
+        ]
+    );
+
+    // `B` is only substituted into variables after it, so the recursion of
+    // `S` behind it stays and is reported.
+    let src = "S -> B S x | y\nB -> b | ε";
+    let mut c: CFG<()> = CFG::from_bnf(src).unwrap();
+    assert_eq!(
+        c.left_recursion_eliminate().unwrap_err().to_string(),
+        "left recursion of `S` is hidden behind a nullable prefix"
+    );
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec!["S => B S x", "S => y", "B => b", "B => #"]
+    );
+
+    // With `B` first it is substituted and the recursion becomes direct.
+    let mut c: CFG<()> = CFG::from_bnf("T -> S\nB -> b | ε\nS -> B S x | y").unwrap();
+    c.left_recursion_eliminate().unwrap();
+    assert_eq!(
+        c.rules.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        vec![
+            "T => S",
+            "S => b S x S'",
+            "S => y S'",
+            "S' => x S'",
+            "S' => #"
+        ]
+    );
+}
+
+#[test]
+fn test_left_recursive_sets() {
+    let c: CFG<()> =
+        CFG::from_bnf("E -> E a T | T\nT -> T m F | F\nF -> l E r | d | A\nA -> ε").unwrap();
+    let firsts = c.get_firsts();
+    let set = |v: &[&str]| {
+        v.iter()
+            .map(|t| Symbol::Terminal(t.to_string()))
+            .collect::<HashSet<_>>()
+    };
+    let mut e_first = set(&["l", "d", "a", "m"]);
+    e_first.insert(Symbol::Epsilon);
+    assert_eq!(firsts.0["E"], e_first);
+    assert_eq!(firsts.0["A"], vec![Symbol::Epsilon].into_iter().collect());
+    let follows = c.get_follows();
+    let mut t_follow = set(&["a", "m", "r"]);
+    t_follow.insert(Symbol::EndOfInput);
+    assert_eq!(follows.0["T"], t_follow);
+}
+
+#[test]
+fn test_end_of_input() {
+    #[derive(Debug)]
+    enum Token {
+        A,
+        B,
+    }
+    let mut c: CFG<Token> = CFG::from_bnf("S -> X Y\nX -> a | ε\nY -> b | ε").unwrap();
+    c.bind_terminal("a", &Token::A).unwrap();
+    c.bind_terminal("b", &Token::B).unwrap();
+    let follows = c.get_follows();
+    assert_eq!(
+        follows.0["X"],
+        vec![Symbol::Terminal("b".into()), Symbol::EndOfInput]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(
+        follows.0["Y"],
+        vec![Symbol::EndOfInput].into_iter().collect()
+    );
+    let table = c.get_table().unwrap();
+    assert_eq!(
+        table.rules[table.cells[&("Y".to_string(), Symbol::EndOfInput)]].to_string(),
+        "Y => #"
+    );
+    assert!(c.parse(&[]).is_ok());
+    assert!(c.parse(&[Token::A, Token::B]).is_ok());
+    assert!(c.parse(&[Token::B]).is_ok());
+    assert!(c.parse(&[Token::A, Token::A]).is_err());
+    assert!(c.parse(&[Token::B, Token::B]).is_err());
+}
+
+#[test]
+fn test_table_format() {
+    let c: CFG<()> = CFG::from_bnf("L -> id R\nR -> , id R | ε").unwrap();
+    let table = c.get_table().unwrap();
+    assert_eq!(
+        table.to_string(),
+        "  | id        | ,           | $\n\
+         L | L => id R |             |\n\
+         R |           | R => , id R | R => #\n"
+    );
+    assert_eq!(
+        table.to_markdown(),
+        "|  | id | , | $ |\n|---|---|---|---|\n\
+         | L | L => id R |  |  |\n\
+         | R |  | R => , id R | R => # |\n"
+    );
+    assert_eq!(
+        table.to_csv(),
+        ",id,\",\",$\nL,L => id R,,\nR,,\"R => , id R\",R => #\n"
+    );
+    assert!(table
+        .to_html()
+        .contains("<tr><th>R</th><td></td><td>R =&gt; , id R</td><td>R =&gt; #</td></tr>"));
+}
+
+#[test]
+fn test_parse_with_recovery() {
+    use crate::fixtures::{arith_grammar, Token, Token::*};
+    let c = arith_grammar();
+    let messages = |tokens: &[Token]| {
+        let (_, errors) = c.parse_with_recovery(tokens).unwrap();
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+    };
+    assert!(messages(&[Num(1), Add, Num(2)]).is_empty());
+    assert_eq!(
+        messages(&[RightBracket, Num(1), Mul, Add, Num(2), Add, RightBracket]),
+        vec![
+            "expected one of `l`, `d` but found `r` at token 0",
+            "expected one of `l`, `d` but found `a` at token 3",
+            "expected one of `l`, `d` but found `r` at token 6",
+        ]
+    );
+    // The tokens discarded while looking for a synchronizing token are
+    // covered by the error that started the run.
+    assert_eq!(
+        messages(&[Num(1), Add, Mul, Mul, Mul, Num(2)]),
+        vec!["expected one of `l`, `d` but found `m` at token 2"]
+    );
+    assert_eq!(
+        messages(&[LeftBracket, Num(1), Add, Num(2), Num(3)]),
+        vec![
+            "expected one of `a`, `m`, `r`, `$` but found `d` at token 4",
+            "expected `r` but found `$` at token 5",
+        ]
+    );
+    assert_eq!(
+        c.parse(&[LeftBracket, Num(1)]).unwrap_err(),
+        ParseError::UnexpectedToken {
+            index: 2,
+            found: Symbol::EndOfInput,
+            expected: vec![Symbol::Terminal("r".into())],
+        }
+    );
+    assert_eq!(
+        c.parse(&[Num(1), Num(2)]).unwrap_err().to_string(),
+        "expected one of `a`, `m`, `r`, `$` but found `d` at token 1"
+    );
+}
+
+#[test]
+fn test_parse_trace() {
+    use crate::fixtures::{list_grammar, ListToken::*};
+    let c = list_grammar();
+    let (tree, trace) = c.parse_trace(&[Id, Comma, Id]);
+    assert!(tree.is_ok());
+    println!("{}", trace);
+    assert_eq!(
+        trace.to_string(),
+        "Stack    | Input     | Action
+$ L      | id c id $ | output L => id R
+$ R id   | id c id $ | match id
+$ R      | c id $    | output R => c id R
+$ R id c | c id $    | match c
+$ R id   | id $      | match id
+$ R      | $         | output R => #
+$        | $         | accept
+"
+    );
+
+    let (tree, trace) = c.parse_trace(&[Id, Id]);
+    assert_eq!(tree.unwrap_err().index(), Some(1));
+    assert_eq!(
+        trace.0.last().unwrap().action.to_string(),
+        "error: expected one of `c`, `$` but found `id` at token 1"
+    );
+}
+
+#[test]
+fn test_semantic_actions() {
+    use crate::fixtures::{arith_grammar, Token::*};
+
+    // As in `main`, which eliminates left recursion first and finds none.
+    let mut c = arith_grammar();
+    c.left_recursion_eliminate().unwrap();
+    c.left_factor().unwrap();
+    let tokens = [LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)];
+    assert_eq!(c.evaluate(&tokens), Ok(9));
+    assert_eq!(
+        c.evaluate(&[Num(1), Add]).unwrap_err().to_string(),
+        "expected one of `l`, `d` but found `$` at token 2"
+    );
+    let tree = c.parse_lr(&c.get_lalr_table().unwrap(), &tokens).unwrap();
+    assert_eq!(c.eval_tree(&tree), 9);
+
+    // Actions belong to productions, not to their sides: the Earley trees
+    // of `S => d d` tell the two `S` productions apart.
+    let c = context_free_grammar!(
+        terminals: {
+            d = Num(0)
+        }
+        value: { i32 }
+        rules: {
+            S => d d { |_| 1 } | d d { |_| 2 };
+        }
+        start: S
+    );
+    let trees = c.parse_earley(&[Num(1), Num(2)], 10).unwrap();
+    let values: Vec<_> = trees.iter().map(|t| c.eval_tree(t)).collect();
+    assert_eq!(values, vec![1, 2]);
+
+    // Rewrites that would change a production with an action fail.
+    let mut c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            d = Num(0)
+        }
+        value: { i32 }
+        rules: {
+            E => E a d { |v| v[0].value() + 1 } | d { |_| 0 };
+        }
+        start: E
+    );
+    let rules = c.rules.clone();
+    assert_eq!(
+        c.left_recursion_eliminate().unwrap_err().to_string(),
+        "cannot rewrite `E => E a d`, which has a semantic action"
+    );
+    assert_eq!(c.rules, rules);
+    let mut c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            d = Num(0)
+        }
+        value: { i32 }
+        rules: {
+            E => d a d { |_| 1 } | d;
+        }
+        start: E
+    );
+    let rules = c.rules.clone();
+    assert_eq!(c.left_factor(), Err(RewriteError::Action(rules[0].clone())));
+    assert_eq!(c.rules, rules);
+
+    // A typed AST from the LR parser, `E => T` passing the value of `T` up.
+    #[derive(Debug, PartialEq)]
+    enum Expr {
+        Num(i32),
+        Add(Box<Expr>, Box<Expr>),
+        Mul(Box<Expr>, Box<Expr>),
+    }
+    let c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            l = LeftBracket,
+            r = RightBracket,
+            d = Num(0)
+        }
+        precedence: {
+            left a;
+            left m;
+        }
+        value: { Option<Expr> }
+        rules: {
+            E => E a E {
+                |v| {
+                    let mut v = v.into_iter();
+                    let l = v.next().unwrap().into_value()?;
+                    let r = v.nth(1).unwrap().into_value()?;
+                    Some(Expr::Add(Box::new(l), Box::new(r)))
+                }
+            } | E m E {
+                |v| {
+                    let mut v = v.into_iter();
+                    let l = v.next().unwrap().into_value()?;
+                    let r = v.nth(1).unwrap().into_value()?;
+                    Some(Expr::Mul(Box::new(l), Box::new(r)))
+                }
+            } | T;
+            T => l E r { |v| v.into_iter().nth(1).unwrap().into_value() } | d {
+                |v| match v[0].token() {
+                    Num(n) => Some(Expr::Num(*n)),
+                    _ => None,
+                }
+            };
+        }
+        start: E
+    );
+    let tree = c.parse_lr(&c.get_lalr_table().unwrap(), &tokens).unwrap();
+    assert_eq!(
+        c.eval_tree(&tree),
+        Some(Expr::Mul(
+            Box::new(Expr::Add(Box::new(Expr::Num(1)), Box::new(Expr::Num(2)))),
+            Box::new(Expr::Num(3))
+        ))
+    );
+}
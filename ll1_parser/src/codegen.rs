@@ -0,0 +1,516 @@
+use crate::cfg::{Conflict, Production, Symbol, Table, CFG};
+#[cfg(test)]
+use crate::context_free_grammar;
+use std::collections::HashMap;
+
+/// `name` as a CamelCase Rust identifier, with `'` spelled `Prime`. `None`
+/// when it has other characters that cannot appear in an identifier.
+fn camel_case(name: &str) -> Option<String> {
+    let mut ident = String::new();
+    for c in name.chars() {
+        match c {
+            '\'' => ident += "Prime",
+            c if c.is_ascii_alphanumeric() || c == '_' => ident.push(c),
+            _ => return None,
+        }
+    }
+    let mut chars = ident.chars();
+    let first = chars.next()?;
+    if first.is_ascii_digit() {
+        return None;
+    }
+    Some(first.to_ascii_uppercase().to_string() + chars.as_str())
+}
+
+/// `name` as a snake_case suffix, with `'` spelled `_prime`.
+fn snake_case(name: &str) -> Option<String> {
+    let mut ident = String::new();
+    for c in name.chars() {
+        match c {
+            '\'' => ident += "_prime",
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                if c.is_ascii_uppercase() && !ident.is_empty() && !ident.ends_with('_') {
+                    ident.push('_');
+                }
+                ident.push(c.to_ascii_lowercase());
+            }
+            _ => return None,
+        }
+    }
+    Some(ident)
+}
+
+/// Types the generated parsers define or name unqualified, which the tree
+/// types of the variables must not shadow.
+const RESERVED_TYPES: [&str; 10] = [
+    "Terminal",
+    "ParseError",
+    "Parser",
+    "Sym",
+    "Rule",
+    "Box",
+    "Option",
+    "Result",
+    "Vec",
+    "Self",
+];
+
+/// Rust names for grammar symbols. Names that are not valid identifiers, are
+/// `reserved`, or collide once converted, fall back to `fallback` and their
+/// index.
+fn idents(
+    names: &[String],
+    convert: fn(&str) -> Option<String>,
+    reserved: &[&str],
+    fallback: &str,
+) -> Vec<String> {
+    let mut idents: Vec<String> = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let ident = match convert(name) {
+            Some(ident)
+                if !ident.is_empty()
+                    && !reserved.contains(&ident.as_str())
+                    && !idents.contains(&ident) =>
+            {
+                ident
+            }
+            _ => format!("{}{}", fallback, i),
+        };
+        idents.push(ident);
+    }
+    idents
+}
+
+/// Names shared by the generated parsers: the `Terminal` variants and a type
+/// and a function suffix for every variable.
+pub(crate) struct Names {
+    pub terminals: HashMap<String, String>,
+    pub types: HashMap<String, String>,
+    pub functions: HashMap<String, String>,
+}
+
+impl<T, V> CFG<T, V> {
+    pub(crate) fn get_names(&self) -> Names {
+        let variables = self.get_variables();
+        let zip =
+            |idents: Vec<String>, names: &[String]| names.iter().cloned().zip(idents).collect();
+        Names {
+            terminals: zip(
+                idents(&self.terminals, camel_case, &["Self"], "T"),
+                &self.terminals,
+            ),
+            types: zip(
+                idents(&variables, camel_case, &RESERVED_TYPES, "Var"),
+                &variables,
+            ),
+            functions: zip(idents(&variables, snake_case, &[], "var"), &variables),
+        }
+    }
+
+    /// The `Terminal` enum, the `ParseError` type and the `TERMINALS` names
+    /// every generated parser starts with.
+    pub(crate) fn generate_prelude(&self, names: &Names, out: &mut String) {
+        *out += "#[derive(Debug, Clone, Copy, PartialEq, Eq)]\npub enum Terminal {\n";
+        for t in self.terminals.iter() {
+            *out += &format!("    /// `{}`\n    {},\n", t, names.terminals[t]);
+        }
+        *out += "}\n\n";
+        *out += &format!(
+            "pub const TERMINALS: [&str; {}] = [{}];\n\n",
+            self.terminals.len(),
+            self.terminals
+                .iter()
+                .map(|t| format!("{:?}", t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        *out += "#[derive(Debug, PartialEq)]\n";
+        *out += "pub struct ParseError {\n";
+        *out += "    pub index: usize,\n";
+        *out += "    pub found: Option<Terminal>,\n";
+        *out += "    pub expected: &'static [&'static str],\n";
+        *out += "}\n\n";
+    }
+}
+
+impl<T, V> CFG<T, V> {
+    /// Generates the source of a recursive-descent parser for the grammar,
+    /// with one function per variable choosing the production from the LL(1)
+    /// table. The parser reads a slice of the generated `Terminal` enum and
+    /// builds a typed tree: an enum per variable with a variant per
+    /// production, holding the token index of each terminal and the subtree
+    /// of each variable.
+    pub fn generate_recursive_descent(&self) -> Result<String, Vec<Conflict>> {
+        let table = self.get_table()?;
+        let names = self.get_names();
+        let mut out = format!(
+            "// Recursive-descent parser generated by ll1_parser, do not edit.\n\
+             // Grammar start: {}\n\n",
+            self.start
+        );
+        self.generate_prelude(&names, &mut out);
+        for var in table.variables.iter() {
+            self.generate_tree_type(var, &names, &mut out);
+        }
+        out += "pub struct Parser<'t> {\n";
+        out += "    tokens: &'t [Terminal],\n";
+        out += "    pos: usize,\n";
+        out += "}\n\n";
+        out += "impl<'t> Parser<'t> {\n";
+        out += "    pub fn new(tokens: &'t [Terminal]) -> Parser<'t> {\n";
+        out += "        Parser { tokens, pos: 0 }\n";
+        out += "    }\n\n";
+        out += "    fn peek(&self) -> Option<Terminal> {\n";
+        out += "        self.tokens.get(self.pos).copied()\n";
+        out += "    }\n\n";
+        out += "    fn error(&self, expected: &'static [&'static str]) -> ParseError {\n";
+        out += "        ParseError {\n";
+        out += "            index: self.pos,\n";
+        out += "            found: self.peek(),\n";
+        out += "            expected,\n";
+        out += "        }\n";
+        out += "    }\n\n";
+        out += "    fn expect(&mut self, t: Terminal, name: &'static [&'static str]) -> Result<usize, ParseError> {\n";
+        out += "        if self.peek() == Some(t) {\n";
+        out += "            self.pos += 1;\n";
+        out += "            Ok(self.pos - 1)\n";
+        out += "        } else {\n";
+        out += "            Err(self.error(name))\n";
+        out += "        }\n";
+        out += "    }\n";
+        for var in table.variables.iter() {
+            out += "\n";
+            self.generate_function(var, &table, &names, &mut out);
+        }
+        out += "}\n\n";
+        out += &format!(
+            "pub fn parse(tokens: &[Terminal]) -> Result<{}, ParseError> {{\n",
+            names.types[&self.start]
+        );
+        out += "    let mut parser = Parser::new(tokens);\n";
+        out += &format!(
+            "    let tree = parser.parse_{}()?;\n",
+            names.functions[&self.start]
+        );
+        out += "    match parser.peek() {\n";
+        out += "        None => Ok(tree),\n";
+        out += "        Some(_) => Err(parser.error(&[\"$\"])),\n";
+        out += "    }\n";
+        out += "}\n";
+        Ok(out)
+    }
+
+    fn get_var_rules(&self, var: &str) -> Vec<&Production> {
+        self.rules.iter().filter(|r| r.left == var).collect()
+    }
+
+    fn generate_tree_type(&self, var: &str, names: &Names, out: &mut String) {
+        *out += &format!("#[derive(Debug)]\npub enum {} {{\n", names.types[var]);
+        for (k, rule) in self.get_var_rules(var).into_iter().enumerate() {
+            let fields: Vec<_> = rule
+                .right
+                .iter()
+                .filter_map(|s| match s {
+                    Symbol::Terminal(_) => Some("usize".to_string()),
+                    Symbol::Variable(v) => Some(format!("Box<{}>", names.types[v])),
+                    _ => None,
+                })
+                .collect();
+            *out += &format!("    /// `{}`\n    P{}", rule, k);
+            if !fields.is_empty() {
+                *out += &format!("({})", fields.join(", "));
+            }
+            *out += ",\n";
+        }
+        *out += "}\n\n";
+    }
+
+    fn generate_function(&self, var: &str, table: &Table, names: &Names, out: &mut String) {
+        let lookahead = |s: &Symbol| match s {
+            Symbol::Terminal(t) => format!("Some(Terminal::{})", names.terminals[t]),
+            _ => "None".to_string(),
+        };
+        let expected: Vec<_> = table
+            .get_expected(var)
+            .iter()
+            .map(|s| format!("{:?}", s.to_string()))
+            .collect();
+        *out += &format!(
+            "    pub fn parse_{}(&mut self) -> Result<{}, ParseError> {{\n",
+            names.functions[var], names.types[var]
+        );
+        *out += "        match self.peek() {\n";
+        for (k, rule) in self.get_var_rules(var).into_iter().enumerate() {
+            let patterns: Vec<_> = table
+                .terminals
+                .iter()
+                .filter(|t| {
+                    table
+                        .cells
+                        .get(&(var.to_string(), (*t).clone()))
+                        .is_some_and(|p| std::ptr::eq(&table.rules[*p], rule))
+                })
+                .map(lookahead)
+                .collect();
+            if patterns.is_empty() {
+                continue;
+            }
+            *out += &format!("            // {}\n", rule);
+            *out += &format!("            {} => {{\n", patterns.join(" | "));
+            let mut fields = Vec::new();
+            for sym in rule.right.iter() {
+                let x = format!("x{}", fields.len());
+                match sym {
+                    Symbol::Terminal(t) => {
+                        *out += &format!(
+                            "                let {} = self.expect(Terminal::{}, &[{:?}])?;\n",
+                            x, names.terminals[t], t
+                        )
+                    }
+                    Symbol::Variable(v) => {
+                        *out += &format!(
+                            "                let {} = Box::new(self.parse_{}()?);\n",
+                            x, names.functions[v]
+                        )
+                    }
+                    _ => continue,
+                }
+                fields.push(x);
+            }
+            *out += &format!("                Ok({}::P{}", names.types[var], k);
+            if !fields.is_empty() {
+                *out += &format!("({})", fields.join(", "));
+            }
+            *out += ")\n            }\n";
+        }
+        *out += &format!(
+            "            _ => Err(self.error(&[{}])),\n",
+            expected.join(", ")
+        );
+        *out += "        }\n    }\n";
+    }
+}
+
+impl<T, V> CFG<T, V> {
+    /// Generates a module holding the LL(1) table as static arrays indexed by
+    /// variable and terminal id, with a driver that needs no hashing or
+    /// allocation besides its stack. Meant to be written out from `build.rs`
+    /// and pulled in with `include!`. `parse` returns the productions of the
+    /// leftmost derivation, indices into `RULES`.
+    pub fn generate_table_parser(&self) -> Result<String, Vec<Conflict>> {
+        let table = self.get_table()?;
+        let names = self.get_names();
+        let var_ids: HashMap<_, _> = table
+            .variables
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.clone(), i))
+            .collect();
+        let mut out = format!(
+            "// Table-driven parser generated by ll1_parser, do not edit.\n\
+             // Grammar start: {}\n\n",
+            self.start
+        );
+        self.generate_prelude(&names, &mut out);
+        out += "#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n";
+        out += "pub enum Sym {\n    T(Terminal),\n    V(u16),\n}\n\n";
+        out += "#[derive(Debug)]\n";
+        out += "pub struct Rule {\n    pub left: u16,\n    pub right: &'static [Sym],\n}\n\n";
+        out += &format!(
+            "pub const VARIABLES: [&str; {}] = [{}];\n\n",
+            table.variables.len(),
+            table
+                .variables
+                .iter()
+                .map(|v| format!("{:?}", v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        out += &format!("pub const START: u16 = {};\n", var_ids[&self.start]);
+        out += &format!("const END: usize = {};\n\n", self.terminals.len());
+
+        out += &format!("pub static RULES: [Rule; {}] = [\n", self.rules.len());
+        for rule in self.rules.iter() {
+            let right: Vec<_> = rule
+                .right
+                .iter()
+                .filter_map(|s| match s {
+                    Symbol::Terminal(t) => {
+                        Some(format!("Sym::T(Terminal::{})", names.terminals[t]))
+                    }
+                    Symbol::Variable(v) => Some(format!("Sym::V({})", var_ids[v])),
+                    _ => None,
+                })
+                .collect();
+            out += &format!("    // {}\n", rule);
+            out += &format!(
+                "    Rule {{ left: {}, right: &[{}] }},\n",
+                var_ids[&rule.left],
+                right.join(", ")
+            );
+        }
+        out += "];\n\n";
+
+        out += &format!(
+            "pub static TABLE: [[Option<u16>; {}]; {}] = [\n",
+            table.terminals.len(),
+            table.variables.len()
+        );
+        for var in table.variables.iter() {
+            let row: Vec<_> = table
+                .terminals
+                .iter()
+                .map(|t| match table.cells.get(&(var.clone(), t.clone())) {
+                    Some(p) => format!("Some({})", p),
+                    None => "None".into(),
+                })
+                .collect();
+            out += &format!("    // {}\n    [{}],\n", var, row.join(", "));
+        }
+        out += "];\n\n";
+
+        out += &format!(
+            "static EXPECTED: [&[&str]; {}] = [\n",
+            table.variables.len()
+        );
+        for var in table.variables.iter() {
+            let expected: Vec<_> = table
+                .get_expected(var)
+                .iter()
+                .map(|s| format!("{:?}", s.to_string()))
+                .collect();
+            out += &format!("    &[{}],\n", expected.join(", "));
+        }
+        out += "];\n\n";
+
+        out += "pub fn parse(tokens: &[Terminal]) -> Result<Vec<u16>, ParseError> {\n";
+        out += "    let mut stack = vec![Sym::V(START)];\n";
+        out += "    let mut derivation = Vec::new();\n";
+        out += "    let mut pos = 0;\n";
+        out += "    let error = |pos: usize, expected| ParseError {\n";
+        out += "        index: pos,\n";
+        out += "        found: tokens.get(pos).copied(),\n";
+        out += "        expected,\n";
+        out += "    };\n";
+        out += "    while let Some(top) = stack.pop() {\n";
+        out += "        let a = tokens.get(pos).map_or(END, |t| *t as usize);\n";
+        out += "        match top {\n";
+        out += "            Sym::T(t) => {\n";
+        out += "                if t as usize != a {\n";
+        out += "                    return Err(error(pos, std::slice::from_ref(&TERMINALS[t as usize])));\n";
+        out += "                }\n";
+        out += "                pos += 1;\n";
+        out += "            }\n";
+        out += "            Sym::V(v) => match TABLE[v as usize][a] {\n";
+        out += "                Some(p) => {\n";
+        out += "                    derivation.push(p);\n";
+        out += "                    stack.extend(RULES[p as usize].right.iter().rev());\n";
+        out += "                }\n";
+        out += "                None => return Err(error(pos, EXPECTED[v as usize])),\n";
+        out += "            },\n";
+        out += "        }\n";
+        out += "    }\n";
+        out += "    if pos < tokens.len() {\n";
+        out += "        return Err(error(pos, &[\"$\"]));\n";
+        out += "    }\n";
+        out += "    Ok(derivation)\n";
+        out += "}\n";
+        Ok(out)
+    }
+}
+
+#[test]
+fn test_idents() {
+    assert_eq!(camel_case("A'"), Some("APrime".into()));
+    assert_eq!(camel_case("expr"), Some("Expr".into()));
+    assert_eq!(camel_case("+"), None);
+    assert_eq!(snake_case("A'"), Some("a_prime".into()));
+    assert_eq!(snake_case("ExprList"), Some("expr_list".into()));
+    assert_eq!(
+        idents(&["a".into(), "A".into(), ",".into()], camel_case, &[], "T"),
+        vec!["A", "T1", "T2"]
+    );
+    assert_eq!(
+        idents(
+            &["parser".into(), "Terminal".into(), "Expr".into()],
+            camel_case,
+            &RESERVED_TYPES,
+            "Var"
+        ),
+        vec!["Var0", "Var1", "Expr"]
+    );
+}
+
+#[test]
+fn test_generate_recursive_descent() {
+    use crate::fixtures::Token;
+    let mut c = context_free_grammar!(
+        terminals: {
+            a = Token::Add,
+            d = Token::Num(0)
+        }
+        rules: {
+            E => E a d | d;
+        }
+        start: E
+    );
+    assert!(c.generate_recursive_descent().is_err());
+    c.left_recursion_eliminate().unwrap();
+    let src = c.generate_recursive_descent().unwrap();
+    println!("{}", src);
+    assert!(src.contains("pub enum Terminal {\n    /// `a`\n    A,\n    /// `d`\n    D,\n}"));
+    assert!(src.contains("pub enum EPrime {\n    /// `E' => a d E'`\n    P0(usize, usize, Box<EPrime>),\n    /// `E' => #`\n    P1,\n}"));
+    assert!(src.contains("    pub fn parse_e_prime(&mut self) -> Result<EPrime, ParseError> {"));
+    assert!(src.contains("            Some(Terminal::A) => {\n                let x0 = self.expect(Terminal::A, &[\"a\"])?;"));
+    assert!(src.contains("            None => {\n                Ok(EPrime::P1)\n            }"));
+    assert!(src.contains("            _ => Err(self.error(&[\"a\", \"$\"])),"));
+}
+
+#[test]
+fn test_generate_table_parser() {
+    let c = crate::fixtures::list_grammar();
+    let src = c.generate_table_parser().unwrap();
+    println!("{}", src);
+    assert!(src.contains("pub const VARIABLES: [&str; 2] = [\"L\", \"R\"];"));
+    assert!(src.contains(
+        "    // R => c id R\n    Rule { left: 1, right: &[Sym::T(Terminal::C), Sym::T(Terminal::Id), Sym::V(1)] },"
+    ));
+    assert!(src.contains("    // R => #\n    Rule { left: 1, right: &[] },"));
+    assert!(src.contains(
+        "pub static TABLE: [[Option<u16>; 3]; 2] = [\n    // L\n    [Some(0), None, None],\n    // R\n    [None, Some(1), Some(2)],\n];"
+    ));
+    assert!(
+        src.contains("static EXPECTED: [&[&str]; 2] = [\n    &[\"id\"],\n    &[\"c\", \"$\"],\n];")
+    );
+}
+
+/// The parsers under `tests/generated` must be what the generators emit
+/// today, `tests/generated.rs` compiling and running them. Set
+/// `UPDATE_GENERATED` to rewrite them.
+#[test]
+fn test_generated_sources() {
+    let arith = crate::fixtures::arith_grammar();
+    let reserved: CFG<()> = CFG::from_bnf(
+        "Parser -> Terminal ParseError\nTerminal -> id\nParseError -> , id ParseError | ε",
+    )
+    .unwrap();
+    let sources = vec![
+        ("arith_descent.rs", arith.generate_recursive_descent()),
+        ("arith_table.rs", arith.generate_table_parser()),
+        ("reserved_descent.rs", reserved.generate_recursive_descent()),
+    ];
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/generated");
+    for (name, src) in sources {
+        let src = src.unwrap();
+        let path = dir.join(name);
+        if std::env::var_os("UPDATE_GENERATED").is_some() {
+            std::fs::write(&path, &src).unwrap();
+        }
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap_or_default(),
+            src,
+            "{} is out of date, rerun with UPDATE_GENERATED=1",
+            name
+        );
+    }
+}
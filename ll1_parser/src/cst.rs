@@ -0,0 +1,270 @@
+use crate::cfg::{ParseError, Symbol, Tree, CFG};
+#[cfg(test)]
+use crate::context_free_grammar;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::discriminant;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind<T> {
+    /// A variable expanded with `rules[production]`. `production` is `None`
+    /// when error recovery gave up on the variable.
+    Variable {
+        name: String,
+        production: Option<usize>,
+    },
+    /// A token bound to `terminal`.
+    Token {
+        terminal: String,
+        value: T,
+    },
+    /// A terminal missing from the input, see `CFG::parse_with_recovery`.
+    Missing(String),
+    Epsilon,
+}
+
+/// An owned concrete syntax tree. `span` is the range of token indices the
+/// node covers, empty for ε and missing terminals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst<T> {
+    pub kind: NodeKind<T>,
+    pub span: Range<usize>,
+    pub children: Vec<Cst<T>>,
+}
+
+/// Walks a `Cst` depth first, `enter` being called before the children of a
+/// node and `leave` after them.
+pub trait Visitor<T> {
+    fn enter(&mut self, _node: &Cst<T>) {}
+    fn leave(&mut self, _node: &Cst<T>) {}
+}
+
+/// Computes a value bottom up, from a node and the values of its children.
+pub trait Fold<T> {
+    type Output;
+    fn fold(&mut self, node: &Cst<T>, children: Vec<Self::Output>) -> Self::Output;
+}
+
+impl<T> Cst<T> {
+    pub fn walk<V: Visitor<T>>(&self, visitor: &mut V) {
+        visitor.enter(self);
+        for child in self.children.iter() {
+            child.walk(visitor);
+        }
+        visitor.leave(self);
+    }
+
+    pub fn fold<F: Fold<T>>(&self, folder: &mut F) -> F::Output {
+        let children = self.children.iter().map(|c| c.fold(folder)).collect();
+        folder.fold(self, children)
+    }
+
+    /// The tokens under this node, in order.
+    pub fn tokens(&self) -> Vec<&T> {
+        match &self.kind {
+            NodeKind::Token { value, .. } => vec![value],
+            _ => self.children.iter().flat_map(|c| c.tokens()).collect(),
+        }
+    }
+
+    fn write_lines(
+        &self,
+        f: &mut Formatter<'_>,
+        prefix: &str,
+        last: bool,
+        root: bool,
+    ) -> std::fmt::Result
+    where
+        T: Debug,
+    {
+        let (branch, indent) = match (root, last) {
+            (true, _) => ("", ""),
+            (false, true) => ("└── ", "    "),
+            (false, false) => ("├── ", "│   "),
+        };
+        let span = format!("{}..{}", self.span.start, self.span.end);
+        match &self.kind {
+            NodeKind::Variable {
+                name,
+                production: Some(p),
+            } => writeln!(f, "{}{}{} (rule {}) {}", prefix, branch, name, p, span)?,
+            NodeKind::Variable {
+                name,
+                production: None,
+            } => writeln!(f, "{}{}{} (error) {}", prefix, branch, name, span)?,
+            NodeKind::Token { terminal, value } => {
+                writeln!(f, "{}{}{} {:?} {}", prefix, branch, terminal, value, span)?
+            }
+            NodeKind::Missing(t) => writeln!(f, "{}{}{} (missing) {}", prefix, branch, t, span)?,
+            NodeKind::Epsilon => writeln!(f, "{}{}{} {}", prefix, branch, Symbol::Epsilon, span)?,
+        }
+        let prefix = format!("{}{}", prefix, indent);
+        for (i, child) in self.children.iter().enumerate() {
+            child.write_lines(f, &prefix, i + 1 == self.children.len(), false)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the tree one node per line, children indented under their parent.
+impl<T: Debug> Display for Cst<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write_lines(f, "", true, true)
+    }
+}
+
+impl<T: Clone, V> CFG<T, V> {
+    /// Converts a tree returned by any of the parsers into a `Cst`.
+    pub fn build_cst(&self, tree: &Tree<'_, T>) -> Cst<T> {
+        let mut pos = 0;
+        self.build_cst_node(tree, &mut pos)
+    }
+
+    /// Same as `parse`, returning a `Cst`.
+    pub fn parse_cst(&self, tokens: &[T]) -> Result<Cst<T>, ParseError> {
+        let tree = self.parse(tokens)?;
+        Ok(self.build_cst(&tree))
+    }
+
+    /// `pos` is the index past the last token seen, where empty nodes go.
+    fn build_cst_node(&self, tree: &Tree<'_, T>, pos: &mut usize) -> Cst<T> {
+        match tree {
+            Tree::Epsilon => Cst {
+                kind: NodeKind::Epsilon,
+                span: *pos..*pos,
+                children: Vec::new(),
+            },
+            Tree::Leaf { index, token } => {
+                *pos = index + 1;
+                Cst {
+                    kind: NodeKind::Token {
+                        terminal: self.discriminant2terminal[&discriminant(*token)].clone(),
+                        value: (*token).clone(),
+                    },
+                    span: *index..*pos,
+                    children: Vec::new(),
+                }
+            }
+            Tree::Node { name, nodes, .. } if nodes.is_empty() && self.terminals.contains(name) => {
+                Cst {
+                    kind: NodeKind::Missing(name.clone()),
+                    span: *pos..*pos,
+                    children: Vec::new(),
+                }
+            }
+            Tree::Node {
+                name,
+                production,
+                nodes,
+            } => {
+                let start = *pos;
+                let children: Vec<_> = nodes.iter().map(|n| self.build_cst_node(n, pos)).collect();
+                Cst {
+                    kind: NodeKind::Variable {
+                        name: name.clone(),
+                        production: *production,
+                    },
+                    span: children.first().map_or(start, |c| c.span.start)..*pos,
+                    children,
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cst() {
+    use crate::fixtures::{arith_grammar, Token, Token::*};
+    let c = arith_grammar();
+    let tokens = vec![Num(1), Add, Num(2)];
+    let cst = c.parse_cst(&tokens).unwrap();
+    assert_eq!(
+        cst.to_string(),
+        "E (rule 0) 0..3
+├── T (rule 3) 0..1
+│   ├── F (rule 7) 0..1
+│   │   └── d Num(1) 0..1
+│   └── B (rule 5) 1..1
+│       └── # 1..1
+└── A (rule 1) 1..3
+    ├── a Add 1..2
+    ├── T (rule 3) 2..3
+    │   ├── F (rule 7) 2..3
+    │   │   └── d Num(2) 2..3
+    │   └── B (rule 5) 3..3
+    │       └── # 3..3
+    └── A (rule 2) 3..3
+        └── # 3..3
+"
+    );
+    assert_eq!(cst.tokens(), vec![&Num(1), &Add, &Num(2)]);
+
+    // The same tree comes out of the LR parser.
+    let lr = c.parse_lr(&c.get_lalr_table().unwrap(), &tokens).unwrap();
+    assert_eq!(c.build_cst(&lr), cst);
+
+    struct Depth(usize, usize);
+    impl Visitor<Token> for Depth {
+        fn enter(&mut self, _: &Cst<Token>) {
+            self.0 += 1;
+            self.1 = self.1.max(self.0);
+        }
+        fn leave(&mut self, _: &Cst<Token>) {
+            self.0 -= 1;
+        }
+    }
+    let mut depth = Depth(0, 0);
+    cst.walk(&mut depth);
+    assert_eq!(depth.1, 5);
+
+    struct Eval;
+    impl Fold<Token> for Eval {
+        type Output = i32;
+        fn fold(&mut self, node: &Cst<Token>, children: Vec<i32>) -> i32 {
+            match &node.kind {
+                NodeKind::Token { value: Num(n), .. } => *n,
+                _ => children.iter().sum(),
+            }
+        }
+    }
+    assert_eq!(cst.fold(&mut Eval), 3);
+
+    // Recovery gives up on the `F` after the first `m` and inserts `r`.
+    let tokens = vec![LeftBracket, Num(1), Mul, Mul, Num(2)];
+    let (tree, errors) = c.parse_with_recovery(&tokens).unwrap();
+    assert_eq!(errors.len(), 2);
+    let cst = c.build_cst(&tree);
+    let lines = cst.to_string();
+    assert!(lines.contains("│   │   │   │       ├── F (error) 3..3\n"));
+    assert!(lines.contains("│   │   └── r (missing) 5..5\n"));
+    assert_eq!(cst.tokens().len(), 5);
+    assert_eq!(cst.span, 0..5);
+
+    // `A => d` and `B => d` have the same shape, the parser tells which.
+    let c = context_free_grammar!(
+        terminals: {
+            d = Num(0)
+        }
+        rules: {
+            S => A | B;
+            A => d;
+            B => d | d;
+        }
+        start: S
+    );
+    let trees = c.parse_earley(&[Num(7)], 10).unwrap();
+    let productions: Vec<_> = trees
+        .iter()
+        .map(|t| {
+            let cst = c.build_cst(t);
+            match (&cst.kind, &cst.children[0].kind) {
+                (
+                    NodeKind::Variable { production: p, .. },
+                    NodeKind::Variable { production: q, .. },
+                ) => (p.unwrap(), q.unwrap()),
+                _ => unreachable!(),
+            }
+        })
+        .collect();
+    assert_eq!(productions, vec![(0, 2), (1, 3), (1, 4)]);
+}
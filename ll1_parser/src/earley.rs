@@ -0,0 +1,464 @@
+use crate::cfg::{ParseError, Production, Symbol, Tree, CFG};
+#[cfg(test)]
+use crate::context_free_grammar;
+use crate::lr::body;
+#[cfg(test)]
+use crate::lr::format_tree;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// Production `production` of the augmented grammar with the dot before its
+/// `dot`-th symbol, started at token `origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EarleyItem {
+    pub production: usize,
+    pub dot: usize,
+    pub origin: usize,
+}
+
+/// The Earley sets, `sets[j]` holding the items after reading `j` tokens.
+/// `rules` is the augmented grammar, whose production 0 is `S' => S`.
+#[derive(Debug)]
+pub struct EarleyChart {
+    pub rules: Vec<Production>,
+    pub sets: Vec<Vec<EarleyItem>>,
+}
+
+impl EarleyChart {
+    pub fn next_symbol(&self, item: &EarleyItem) -> Option<&Symbol> {
+        body(&self.rules[item.production]).get(item.dot)
+    }
+
+    /// Whether the start variable derives the whole input.
+    pub fn is_accepted(&self) -> bool {
+        self.sets.last().unwrap().contains(&EarleyItem {
+            production: 0,
+            dot: 1,
+            origin: 0,
+        })
+    }
+
+    pub fn format_item(&self, item: &EarleyItem) -> String {
+        let rule = &self.rules[item.production];
+        let mut right: Vec<_> = body(rule).iter().map(|s| s.to_string()).collect();
+        right.insert(item.dot, ".".into());
+        format!("{} => {}, {}", rule.left, right.join(" "), item.origin)
+    }
+}
+
+fn add(set: &mut Vec<EarleyItem>, item: EarleyItem) {
+    if !set.contains(&item) {
+        set.push(item);
+    }
+}
+
+/// Enumerates the derivations recorded by a successful parse. The trees of
+/// a variable and the sequences of the rest of a production are worked out
+/// once per span and memoized, keeping only the first `limit` of them, which
+/// are all any larger tree picks from. So the work grows with the input and
+/// `limit` rather than with the number of derivations, except where cyclic
+/// derivations are cut.
+struct Forest<'c, 'a, T, V> {
+    cfg: &'c CFG<T, V>,
+    rules: &'c [Production],
+    tokens: &'a [T],
+    /// `(production, from, to)` for every production completed over a span.
+    completed: HashSet<(usize, usize, usize)>,
+    limit: usize,
+    /// The `(var, from, to)` being expanded, outermost first.
+    active: Vec<(String, usize, usize)>,
+    trees: HashMap<(String, usize, usize), Vec<Tree<'a, T>>>,
+    /// Keyed by `(production, dot, from, to)`.
+    sequences: HashMap<(usize, usize, usize, usize), Sequences<'a, T>>,
+}
+
+type Sequences<'a, T> = Vec<Vec<Tree<'a, T>>>;
+
+impl<'a, T, V> Forest<'_, 'a, T, V> {
+    /// The trees of `var` over `from..to`. A variable already being expanded
+    /// over the same span is skipped, which cuts cyclic derivations. Along
+    /// with the trees comes whether any was cut: what is cut depends on the
+    /// variables being expanded around, so such a result is not memoized.
+    fn trees(&mut self, var: &str, from: usize, to: usize) -> (Vec<Tree<'a, T>>, bool) {
+        let key = (var.to_string(), from, to);
+        if let Some(trees) = self.trees.get(&key) {
+            return (trees.clone(), false);
+        }
+        if self.active.contains(&key) {
+            return (Vec::new(), true);
+        }
+        self.active.push(key);
+        let rules = self.rules;
+        let mut trees = Vec::new();
+        let mut cut = false;
+        for (p, rule) in rules.iter().enumerate() {
+            if trees.len() == self.limit {
+                break;
+            }
+            if rule.left != var || !self.completed.contains(&(p, from, to)) {
+                continue;
+            }
+            let (sequences, c) = self.sequences(p, 0, from, to);
+            cut |= c;
+            for nodes in sequences.into_iter().take(self.limit - trees.len()) {
+                // The rules are augmented, `rules[0]` being the new start.
+                trees.push(Tree::Node {
+                    name: var.to_string(),
+                    production: Some(p - 1),
+                    nodes: if nodes.is_empty() {
+                        vec![Tree::Epsilon]
+                    } else {
+                        nodes
+                    },
+                });
+            }
+        }
+        let key = self.active.pop().unwrap();
+        if !cut {
+            self.trees.insert(key, trees.clone());
+        }
+        (trees, cut)
+    }
+
+    /// The ways the symbols of `rules[p]` from the `dot`-th on derive
+    /// exactly `from..to`, and whether any was cut as in `trees`.
+    fn sequences(
+        &mut self,
+        p: usize,
+        dot: usize,
+        from: usize,
+        to: usize,
+    ) -> (Sequences<'a, T>, bool) {
+        let key = (p, dot, from, to);
+        if let Some(sequences) = self.sequences.get(&key) {
+            return (sequences.clone(), false);
+        }
+        let rules = self.rules;
+        let first = match body(&rules[p]).get(dot) {
+            Some(first) => first,
+            None => {
+                let sequences = if from == to {
+                    vec![Vec::new()]
+                } else {
+                    Vec::new()
+                };
+                return (sequences, false);
+            }
+        };
+        let mut sequences = Vec::new();
+        let mut cut = false;
+        'mids: for mid in from..=to {
+            let token =
+                mid == from + 1 && self.cfg.get_terminal(self.tokens, from).as_ref() == Some(first);
+            if !token && !matches!(first, Symbol::Variable(_)) {
+                continue;
+            }
+            // The rest first, so no head is built that nothing can follow.
+            // This spares the left-recursive `A => A x` from asking for `A`
+            // over its own span, which would be cut as cyclic.
+            let (tails, c) = self.sequences(p, dot + 1, mid, to);
+            cut |= c;
+            if tails.is_empty() {
+                continue;
+            }
+            let heads = match first {
+                Symbol::Variable(v) => {
+                    let (trees, c) = self.trees(v, from, mid);
+                    cut |= c;
+                    trees
+                }
+                _ => vec![Tree::Leaf {
+                    index: from,
+                    token: &self.tokens[from],
+                }],
+            };
+            for head in heads {
+                for tail in tails.iter() {
+                    if sequences.len() == self.limit {
+                        break 'mids;
+                    }
+                    let mut sequence = vec![head.clone()];
+                    sequence.extend(tail.iter().cloned());
+                    sequences.push(sequence);
+                }
+            }
+        }
+        if !cut {
+            self.sequences.insert(key, sequences.clone());
+        }
+        (sequences, cut)
+    }
+}
+
+impl<T, V> CFG<T, V> {
+    /// Runs the Earley recognizer, which takes any context-free grammar,
+    /// ambiguous or left-recursive. Nullable variables are skipped over as
+    /// they are predicted (Aycock and Horspool), so ε-productions need no
+    /// special completion.
+    pub fn get_earley_chart(&self, tokens: &[T]) -> Result<EarleyChart, ParseError> {
+        let rules = self.get_augmented_rules();
+        let firsts = self.get_firsts();
+        let nullable = |v: &str| firsts.get(v).is_some_and(|f| f.contains(&Symbol::Epsilon));
+        let mut chart = EarleyChart {
+            rules,
+            sets: vec![Vec::new(); tokens.len() + 1],
+        };
+        chart.sets[0].push(EarleyItem {
+            production: 0,
+            dot: 0,
+            origin: 0,
+        });
+        for j in 0..=tokens.len() {
+            let a = self
+                .get_terminal(tokens, j)
+                .ok_or(ParseError::UnknownToken { index: j })?;
+            let mut i = 0;
+            while i < chart.sets[j].len() {
+                let item = chart.sets[j][i];
+                let next = EarleyItem {
+                    dot: item.dot + 1,
+                    ..item
+                };
+                match chart.next_symbol(&item).cloned() {
+                    None => {
+                        let left = &chart.rules[item.production].left;
+                        let waiting: Vec<_> = chart.sets[item.origin]
+                            .iter()
+                            .filter(|x| {
+                                matches!(chart.next_symbol(x), Some(Symbol::Variable(v)) if v == left)
+                            })
+                            .map(|x| EarleyItem { dot: x.dot + 1, ..*x })
+                            .collect();
+                        for x in waiting {
+                            add(&mut chart.sets[j], x);
+                        }
+                    }
+                    Some(Symbol::Variable(v)) => {
+                        for (p, rule) in chart.rules.iter().enumerate() {
+                            if rule.left == v {
+                                let predicted = EarleyItem {
+                                    production: p,
+                                    dot: 0,
+                                    origin: j,
+                                };
+                                add(&mut chart.sets[j], predicted);
+                            }
+                        }
+                        if nullable(&v) {
+                            add(&mut chart.sets[j], next);
+                        }
+                    }
+                    Some(t) => {
+                        if t == a {
+                            add(&mut chart.sets[j + 1], next);
+                        }
+                    }
+                }
+                i += 1;
+            }
+            let stuck = if j < tokens.len() {
+                chart.sets[j + 1].is_empty()
+            } else {
+                !chart.is_accepted()
+            };
+            if stuck {
+                let set = &chart.sets[j];
+                let mut expected: Vec<_> = self
+                    .terminals
+                    .iter()
+                    .map(|t| Symbol::Terminal(t.clone()))
+                    .filter(|t| set.iter().any(|x| chart.next_symbol(x) == Some(t)))
+                    .collect();
+                if set.iter().any(|x| x.production == 0 && x.dot == 1) {
+                    expected.push(Symbol::EndOfInput);
+                }
+                return Err(ParseError::UnexpectedToken {
+                    index: j,
+                    found: a,
+                    expected,
+                });
+            }
+        }
+        Ok(chart)
+    }
+
+    /// Parses with the Earley algorithm and returns up to `limit` of the
+    /// trees of the input, in the order of the productions.
+    pub fn parse_earley<'a>(
+        &self,
+        tokens: &'a [T],
+        limit: usize,
+    ) -> Result<Vec<Tree<'a, T>>, ParseError> {
+        let chart = self.get_earley_chart(tokens)?;
+        let mut completed = HashSet::new();
+        for (to, set) in chart.sets.iter().enumerate() {
+            for item in set {
+                if chart.next_symbol(item).is_none() {
+                    completed.insert((item.production, item.origin, to));
+                }
+            }
+        }
+        Ok(self.get_trees(&chart.rules, tokens, completed, limit))
+    }
+
+    /// Up to `limit` trees of the input, read off the productions of the
+    /// augmented `rules` completed over each span, given as `(production,
+    /// from, to)`, in the order of the productions.
+    pub(crate) fn get_trees<'a>(
+        &self,
+        rules: &[Production],
+        tokens: &'a [T],
+        completed: HashSet<(usize, usize, usize)>,
+        limit: usize,
+    ) -> Vec<Tree<'a, T>> {
+        let mut forest = Forest {
+            cfg: self,
+            rules,
+            tokens,
+            completed,
+            limit,
+            active: Vec::new(),
+            trees: HashMap::new(),
+            sequences: HashMap::new(),
+        };
+        forest.trees(&self.start, 0, tokens.len()).0
+    }
+}
+
+impl Display for EarleyChart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (j, set) in self.sets.iter().enumerate() {
+            writeln!(f, "S{}:", j)?;
+            for item in set {
+                writeln!(f, "  {}", self.format_item(item))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_earley_ambiguous() {
+    use crate::fixtures::Token::{Add, Mul, Num};
+    let c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            d = Num(0)
+        }
+        rules: {
+            E => E a E | E m E | d;
+        }
+        start: E
+    );
+    let chart = c.get_earley_chart(&[Num(1), Add, Num(2)]).unwrap();
+    println!("{}", chart);
+    assert!(chart.is_accepted());
+
+    let trees = c
+        .parse_earley(&[Num(1), Add, Num(2), Mul, Num(3)], 10)
+        .unwrap();
+    let trees: Vec<_> = trees.iter().map(format_tree).collect();
+    assert_eq!(
+        trees,
+        vec![
+            "E(E(Num(1)) Add E(E(Num(2)) Mul E(Num(3))))",
+            "E(E(E(Num(1)) Add E(Num(2))) Mul E(Num(3)))",
+        ]
+    );
+    let tokens = [Num(1), Add, Num(2), Add, Num(3), Add, Num(4)];
+    assert_eq!(5, c.parse_earley(&tokens, 10).unwrap().len());
+    assert_eq!(3, c.parse_earley(&tokens, 3).unwrap().len());
+
+    // Far more trees than could be enumerated, only the first are built.
+    let mut tokens = vec![Num(0)];
+    for _ in 0..40 {
+        tokens.extend(vec![Add, Num(0)]);
+    }
+    assert_eq!(3, c.parse_earley(&tokens, 3).unwrap().len());
+
+    assert_eq!(
+        c.parse_earley(&[Num(1), Add, Mul], 10)
+            .unwrap_err()
+            .to_string(),
+        "expected `d` but found `m` at token 2"
+    );
+    assert_eq!(
+        c.parse_earley(&[Num(1), Add], 10).unwrap_err().to_string(),
+        "expected `d` but found `$` at token 2"
+    );
+}
+
+#[test]
+fn test_earley_epsilon() {
+    #[derive(Debug)]
+    enum Token {
+        Id,
+        Comma,
+    }
+    use Token::{Comma, Id};
+    let c = context_free_grammar!(
+        terminals: {
+            id = Id,
+            c = Comma
+        }
+        rules: {
+            S => A A;
+            A => L | ;
+            L => L c id | id;
+        }
+        start: S
+    );
+    let trees: Vec<_> = c
+        .parse_earley(&[Id, Comma, Id], 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    assert_eq!(
+        trees,
+        vec![
+            "S(A(#) A(L(L(Id) Comma Id)))",
+            "S(A(L(L(Id) Comma Id)) A(#))",
+        ]
+    );
+    assert_eq!(
+        vec!["S(A(#) A(#))"],
+        c.parse_earley(&[], 10)
+            .unwrap()
+            .iter()
+            .map(format_tree)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_earley_cyclic() {
+    use crate::fixtures::Token::Num;
+    let c = context_free_grammar!(
+        terminals: {
+            d = Num(0)
+        }
+        rules: {
+            S => A | B;
+            A => B | d;
+            B => A | d;
+        }
+        start: S
+    );
+    let trees: Vec<_> = c
+        .parse_earley(&[Num(1)], 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    assert_eq!(
+        trees,
+        vec![
+            "S(A(B(Num(1))))",
+            "S(A(Num(1)))",
+            "S(B(A(Num(1))))",
+            "S(B(Num(1)))",
+        ]
+    );
+}
@@ -0,0 +1,67 @@
+//! Tokens and grammars shared by the tests.
+
+use crate::cfg::CFG;
+use crate::context_free_grammar;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Add,
+    Mul,
+    Pow,
+    Less,
+    LeftBracket,
+    RightBracket,
+    Num(i32),
+}
+
+/// The arithmetic grammar of `main`, with left recursion eliminated by hand.
+/// `A` and `B` evaluate to the sum and product of what follows them.
+pub fn arith_grammar() -> CFG<Token, i32> {
+    use Token::*;
+    context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            l = LeftBracket,
+            r = RightBracket,
+            d = Num(0)
+        }
+        value: { i32 }
+        rules: {
+            E => T A { |v| v[0].value() + v[1].value() };
+            A => a T A { |v| v[1].value() + v[2].value() } | { |_| 0 };
+            T => F B { |v| v[0].value() * v[1].value() };
+            B => m F B { |v| v[1].value() * v[2].value() } | { |_| 1 };
+            F => l E r { |v| *v[1].value() } | d {
+                |v| match v[0].token() {
+                    Num(n) => *n,
+                    _ => unreachable!(),
+                }
+            };
+        }
+        start: E
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListToken {
+    Id,
+    Comma,
+}
+
+/// A comma separated list of ids. `R` derives ε at the end of the list, so
+/// its FOLLOW set decides the table entry for `$`.
+pub fn list_grammar() -> CFG<ListToken> {
+    use ListToken::*;
+    context_free_grammar!(
+        terminals: {
+            id = Id,
+            c = Comma
+        }
+        rules: {
+            L => id R;
+            R => c id R | ;
+        }
+        start: L
+    )
+}
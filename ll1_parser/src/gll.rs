@@ -0,0 +1,343 @@
+use crate::cfg::{MultiTable, ParseError, Production, Symbol, Tree, CFG};
+#[cfg(test)]
+use crate::context_free_grammar;
+use crate::lr::body;
+#[cfg(test)]
+use crate::lr::format_tree;
+use std::collections::{HashMap, HashSet};
+
+/// A node of the graph-structured stack, standing for the calls of a
+/// variable made from one slot at one index. `slot` is where the callers
+/// resume, production `p` of the augmented grammar after `dot` symbols, and
+/// `edges` lead to the nodes of the callers. Every caller shares the node,
+/// so the stacks below it are never copied.
+struct GssNode {
+    slot: Option<(usize, usize)>,
+    index: usize,
+    edges: Vec<usize>,
+    /// The indices the variable has returned at so far, which callers that
+    /// come later resume from too.
+    popped: Vec<usize>,
+}
+
+/// The bottom of the stack, returned to when `S' => S` is done.
+const ROOT: usize = 0;
+
+/// Production `p` of the augmented grammar read up to `dot`, called at the
+/// GSS node `node` and with the input read up to `i`, as `(p, dot, node, i)`.
+type Descriptor = (usize, usize, usize, usize);
+
+struct Gll<'c, T, V> {
+    cfg: &'c CFG<T, V>,
+    table: &'c MultiTable<'c>,
+    rules: &'c [Production],
+    tokens: &'c [T],
+    nodes: Vec<GssNode>,
+    ids: HashMap<((usize, usize), usize), usize>,
+    /// Every descriptor ever added, none of which is run twice.
+    seen: HashSet<Descriptor>,
+    work: Vec<Descriptor>,
+    /// `(production, from, to)` for every production completed over a span.
+    completed: HashSet<(usize, usize, usize)>,
+    /// The furthest index a branch failed at and the terminals expected there.
+    error: (usize, Vec<Symbol>),
+}
+
+impl<T, V> Gll<'_, T, V> {
+    fn add(&mut self, descriptor: Descriptor) {
+        if self.seen.insert(descriptor) {
+            self.work.push(descriptor);
+        }
+    }
+
+    fn expect(&mut self, i: usize, expected: Vec<Symbol>) {
+        if i > self.error.0 {
+            self.error = (i, expected);
+        } else if i == self.error.0 {
+            self.error.1.extend(expected);
+        }
+    }
+
+    /// Runs a descriptor until it calls a variable, fails or completes its
+    /// production.
+    fn run(&mut self, (p, mut dot, node, mut i): Descriptor) {
+        let rules = self.rules;
+        let table = self.table;
+        let right = body(&rules[p]);
+        while let Some(sym) = right.get(dot) {
+            let a = self.cfg.get_terminal(self.tokens, i);
+            match sym {
+                Symbol::Variable(v) => {
+                    let cell = a.and_then(|a| table.cells.get(&(v.clone(), a)));
+                    match cell {
+                        Some(cell) => {
+                            let callee = self.call((p, dot + 1), node, i);
+                            for q in cell {
+                                self.add((q + 1, 0, callee, i));
+                            }
+                        }
+                        None => self.expect(i, table.get_expected(v)),
+                    }
+                    return;
+                }
+                _ if a.as_ref() == Some(sym) => {
+                    dot += 1;
+                    i += 1;
+                }
+                _ => {
+                    self.expect(i, vec![sym.clone()]);
+                    return;
+                }
+            }
+        }
+        self.completed.insert((p, self.nodes[node].index, i));
+        self.ret(node, i);
+    }
+
+    /// The node for a call made from `slot` at `i`, with an edge to
+    /// `caller`. A new caller of a variable that has returned already
+    /// resumes from where it did.
+    fn call(&mut self, slot: (usize, usize), caller: usize, i: usize) -> usize {
+        let nodes = &mut self.nodes;
+        let callee = *self.ids.entry((slot, i)).or_insert_with(|| {
+            nodes.push(GssNode {
+                slot: Some(slot),
+                index: i,
+                edges: Vec::new(),
+                popped: Vec::new(),
+            });
+            nodes.len() - 1
+        });
+        if !self.nodes[callee].edges.contains(&caller) {
+            self.nodes[callee].edges.push(caller);
+            for k in self.nodes[callee].popped.clone() {
+                self.add((slot.0, slot.1, caller, k));
+            }
+        }
+        callee
+    }
+
+    /// Returns from the calls of `node` at `i`, resuming every caller.
+    fn ret(&mut self, node: usize, i: usize) {
+        let (p, dot) = match self.nodes[node].slot {
+            Some(slot) => slot,
+            None => {
+                if i < self.tokens.len() {
+                    self.expect(i, vec![Symbol::EndOfInput]);
+                }
+                return;
+            }
+        };
+        if self.nodes[node].popped.contains(&i) {
+            return;
+        }
+        self.nodes[node].popped.push(i);
+        for w in self.nodes[node].edges.clone() {
+            self.add((p, dot, w, i));
+        }
+    }
+}
+
+impl<T, V> CFG<T, V> {
+    /// Generalized LL parsing (Scott and Johnstone) over the table from
+    /// `get_multi_table`. Every production of a cell is tried, the calls of
+    /// a variable at an index sharing one node of a graph-structured stack,
+    /// so ambiguity and left recursion are fine and the work stays
+    /// polynomial. The trees, up to `limit` of them, are read off the
+    /// completed productions as in `parse_earley`.
+    pub fn parse_gll<'a>(
+        &self,
+        tokens: &'a [T],
+        limit: usize,
+    ) -> Result<Vec<Tree<'a, T>>, ParseError> {
+        let table = self.get_multi_table();
+        let rules = self.get_augmented_rules();
+        let mut gll = Gll {
+            cfg: self,
+            table: &table,
+            rules: &rules,
+            tokens,
+            nodes: vec![GssNode {
+                slot: None,
+                index: 0,
+                edges: Vec::new(),
+                popped: Vec::new(),
+            }],
+            ids: HashMap::new(),
+            seen: HashSet::new(),
+            work: Vec::new(),
+            completed: HashSet::new(),
+            error: (0, Vec::new()),
+        };
+        gll.add((0, 0, ROOT, 0));
+        while let Some(descriptor) = gll.work.pop() {
+            gll.run(descriptor);
+        }
+        if gll.completed.contains(&(0, 0, tokens.len())) {
+            return Ok(self.get_trees(&rules, tokens, gll.completed, limit));
+        }
+        let (index, expected) = gll.error;
+        Err(ParseError::UnexpectedToken {
+            index,
+            found: self
+                .get_terminal(tokens, index)
+                .ok_or(ParseError::UnknownToken { index })?,
+            expected: table
+                .terminals
+                .iter()
+                .filter(|t| expected.contains(t))
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+#[test]
+fn test_gll_ambiguous() {
+    #[derive(Debug)]
+    enum Token {
+        If,
+        Then,
+        Else,
+        Other,
+    }
+    use Token::{Else, If, Other, Then};
+    let c = context_free_grammar!(
+        terminals: {
+            i = If,
+            t = Then,
+            e = Else,
+            o = Other
+        }
+        rules: {
+            S => i S t S R | o;
+            R => e S | ;
+        }
+        start: S
+    );
+    assert!(c.get_table().is_err());
+    let table = c.get_multi_table();
+    assert_eq!(
+        table.get_conflicts(),
+        vec![(&"R".to_string(), &Symbol::Terminal("e".into()))]
+    );
+
+    let tokens = [If, Other, Then, If, Other, Then, Other, Else, Other];
+    let mut trees: Vec<_> = c
+        .parse_gll(&tokens, 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    trees.sort();
+    assert_eq!(
+        trees,
+        vec![
+            "S(If S(Other) Then S(If S(Other) Then S(Other) R(#)) R(Else S(Other)))",
+            "S(If S(Other) Then S(If S(Other) Then S(Other) R(Else S(Other))) R(#))",
+        ]
+    );
+    assert_eq!(1, c.parse_gll(&tokens, 1).unwrap().len());
+    assert_eq!(
+        c.parse_gll(&[If, Other, Other], 10)
+            .unwrap_err()
+            .to_string(),
+        "expected `t` but found `o` at token 2"
+    );
+}
+
+#[test]
+fn test_gll_common_prefix() {
+    #[derive(Debug)]
+    enum Token {
+        A,
+        B,
+        C,
+    }
+    let c = context_free_grammar!(
+        terminals: {
+            a = Token::A,
+            b = Token::B,
+            c = Token::C
+        }
+        rules: {
+            S => X c | a b c;
+            X => a b | a X b;
+            E => E a | ;
+        }
+        start: S
+    );
+    let trees: Vec<_> = c
+        .parse_gll(&[Token::A, Token::B, Token::C], 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    assert_eq!(trees.len(), 2);
+    assert!(trees.contains(&"S(X(A B) C)".to_string()));
+    assert!(trees.contains(&"S(A B C)".to_string()));
+    assert!(c
+        .parse_gll(&[Token::A, Token::A, Token::B, Token::B, Token::C], 10)
+        .is_ok());
+
+    let mut c = c;
+    c.start = "E".into();
+    let trees: Vec<_> = c
+        .parse_gll(&[Token::A, Token::A], 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    assert_eq!(trees, vec!["E(E(E(#) A) A)"]);
+    assert_eq!(1, c.parse_gll(&[], 10).unwrap().len());
+    assert_eq!(
+        c.parse_gll(&[Token::A, Token::B], 10)
+            .unwrap_err()
+            .to_string(),
+        "expected one of `a`, `$` but found `b` at token 1"
+    );
+}
+
+#[test]
+fn test_gll_left_recursive() {
+    use crate::fixtures::Token::{Add, Mul, Num};
+    let c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            d = Num(0)
+        }
+        rules: {
+            E => E a E | E m E | d;
+        }
+        start: E
+    );
+    let tokens = [Num(1), Add, Num(2), Mul, Num(3)];
+    let trees: Vec<_> = c
+        .parse_gll(&tokens, 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    let earley: Vec<_> = c
+        .parse_earley(&tokens, 10)
+        .unwrap()
+        .iter()
+        .map(format_tree)
+        .collect();
+    assert_eq!(trees, earley);
+    assert_eq!(trees.len(), 2);
+
+    // Far more trees than could be enumerated, only the first are built.
+    let mut tokens = vec![Num(0)];
+    for _ in 0..40 {
+        tokens.extend(vec![Add, Num(0)]);
+    }
+    assert_eq!(3, c.parse_gll(&tokens, 3).unwrap().len());
+    assert_eq!(
+        c.parse_gll(&[Num(1), Add, Mul], 10)
+            .unwrap_err()
+            .to_string(),
+        "expected `d` but found `m` at token 2"
+    );
+}
@@ -0,0 +1,499 @@
+use crate::cfg::{
+    build_root, Conflict, ConflictKind, ParseError, Production, Symbol, TempNode, Tree, CFG,
+};
+#[cfg(test)]
+use crate::context_free_grammar;
+use std::collections::HashMap;
+use std::mem::{discriminant, Discriminant};
+
+/// A set of integers below the size it was created with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitSet(Vec<u64>);
+
+impl BitSet {
+    pub fn new(size: usize) -> BitSet {
+        BitSet(vec![0; size.div_ceil(64)])
+    }
+
+    /// Returns whether `i` was not in the set yet.
+    pub fn insert(&mut self, i: usize) -> bool {
+        let (word, bit) = (i / 64, 1 << (i % 64));
+        let new = self.0[word] & bit == 0;
+        self.0[word] |= bit;
+        new
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Adds every element of `other`, returning whether any was new.
+    pub fn union_with(&mut self, other: &BitSet) -> bool {
+        let mut changed = false;
+        for (w, o) in self.0.iter_mut().zip(other.0.iter()) {
+            changed |= *o & !*w != 0;
+            *w |= *o;
+        }
+        changed
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.0.len() * 64).filter(move |i| self.contains(*i))
+    }
+}
+
+/// A grammar symbol by id. Terminals are numbered in declaration order, with
+/// `$` right after the last one, and variables in the order of their first
+/// production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolId {
+    Terminal(u32),
+    Variable(u32),
+}
+
+const NO_RULE: u32 = u32::MAX;
+
+/// The grammar of a `CFG` with dense integer ids instead of names, which
+/// are only kept for display and errors. FIRST and FOLLOW are bitsets over
+/// the terminal ids and the LL(1) table a flat array, so `parse` does no
+/// string hashing or cloning while it runs.
+///
+/// This is a read-only snapshot for LL(1) parsing: `CFG` itself, its
+/// transformations and the other parsers keep working on names, and a
+/// grammar changed afterwards has to be interned again.
+#[derive(Debug)]
+pub struct InternedCFG<T> {
+    pub terminals: Vec<String>,
+    pub variables: Vec<String>,
+    /// The productions as `(left, right)`, with ε as an empty right side.
+    pub rules: Vec<(u32, Vec<SymbolId>)>,
+    /// The semantic action of every production, see `Production::action`.
+    pub actions: Vec<Option<usize>>,
+    pub start: u32,
+    pub nullable: BitSet,
+    pub firsts: Vec<BitSet>,
+    pub follows: Vec<BitSet>,
+    /// `table[var * (terminals.len() + 1) + terminal]` is the production to
+    /// expand, or `NO_RULE`.
+    table: Vec<u32>,
+    discriminant2terminal: HashMap<Discriminant<T>, u32>,
+}
+
+impl<T, V> CFG<T, V> {
+    /// Interns the symbols of an LL(1) grammar, see `InternedCFG`. The
+    /// conflicts are the same as those of `get_table`, possibly in another
+    /// order.
+    pub fn intern(&self) -> Result<InternedCFG<T>, Vec<Conflict>> {
+        // Variables used without productions, the start among them, get an
+        // id too. They derive nothing, so their rows of the table are empty.
+        let mut variables = self.get_variables();
+        let used = self.rules.iter().flat_map(|r| r.right.iter());
+        for s in std::iter::once(&Symbol::Variable(self.start.clone())).chain(used) {
+            if let Symbol::Variable(v) = s {
+                if !variables.contains(v) {
+                    variables.push(v.clone());
+                }
+            }
+        }
+        let id = |s: &Symbol| match s {
+            Symbol::Terminal(t) => Some(SymbolId::Terminal(
+                self.terminals.iter().position(|x| x == t).unwrap() as u32,
+            )),
+            Symbol::Variable(v) => Some(SymbolId::Variable(
+                variables.iter().position(|x| x == v).unwrap() as u32,
+            )),
+            Symbol::Epsilon | Symbol::EndOfInput => None,
+        };
+        let rules = self
+            .rules
+            .iter()
+            .map(|r| {
+                let left = variables.iter().position(|x| *x == r.left).unwrap();
+                (left as u32, r.right.iter().filter_map(id).collect())
+            })
+            .collect();
+        let mut cfg = InternedCFG {
+            terminals: self.terminals.clone(),
+            start: variables.iter().position(|x| *x == self.start).unwrap() as u32,
+            nullable: BitSet::new(variables.len()),
+            firsts: Vec::new(),
+            follows: Vec::new(),
+            table: Vec::new(),
+            variables,
+            rules,
+            actions: self.rules.iter().map(|r| r.action).collect(),
+            discriminant2terminal: self
+                .discriminant2terminal
+                .iter()
+                .map(|(d, t)| {
+                    (
+                        *d,
+                        self.terminals.iter().position(|x| x == t).unwrap() as u32,
+                    )
+                })
+                .collect(),
+        };
+        cfg.compute_firsts();
+        cfg.compute_follows();
+        cfg.compute_table()?;
+        Ok(cfg)
+    }
+}
+
+impl<T> InternedCFG<T> {
+    /// The id of `$`, one past the last terminal.
+    pub fn end(&self) -> u32 {
+        self.terminals.len() as u32
+    }
+
+    fn get_terminal_symbol(&self, t: u32) -> Symbol {
+        if t == self.end() {
+            Symbol::EndOfInput
+        } else {
+            Symbol::Terminal(self.terminals[t as usize].clone())
+        }
+    }
+
+    /// FIRST of `str` without ε, and whether `str` is nullable.
+    pub fn get_string_first(&self, str: &[SymbolId]) -> (BitSet, bool) {
+        let mut first = BitSet::new(self.terminals.len() + 1);
+        for s in str {
+            match *s {
+                SymbolId::Terminal(t) => {
+                    first.insert(t as usize);
+                    return (first, false);
+                }
+                SymbolId::Variable(v) => {
+                    first.union_with(&self.firsts[v as usize]);
+                    if !self.nullable.contains(v as usize) {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+        (first, true)
+    }
+
+    fn compute_firsts(&mut self) {
+        self.firsts = vec![BitSet::new(self.terminals.len() + 1); self.variables.len()];
+        loop {
+            let mut changed = false;
+            for (left, right) in self.rules.iter() {
+                let (first, nullable) = self.get_string_first(right);
+                if nullable {
+                    changed |= self.nullable.insert(*left as usize);
+                }
+                let set = &mut self.firsts[*left as usize];
+                changed |= set.union_with(&first);
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn compute_follows(&mut self) {
+        self.follows = vec![BitSet::new(self.terminals.len() + 1); self.variables.len()];
+        let end = self.end() as usize;
+        self.follows[self.start as usize].insert(end);
+        loop {
+            let mut changed = false;
+            for (left, right) in self.rules.iter() {
+                for (i, s) in right.iter().enumerate() {
+                    if let SymbolId::Variable(v) = *s {
+                        let (first, nullable) = self.get_string_first(&right[i + 1..]);
+                        changed |= self.follows[v as usize].union_with(&first);
+                        if nullable && v != *left {
+                            let follow = self.follows[*left as usize].clone();
+                            changed |= self.follows[v as usize].union_with(&follow);
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// `rules[p]` with names, ε spelled out.
+    pub fn get_production(&self, p: usize) -> Production {
+        let (left, right) = &self.rules[p];
+        Production {
+            left: self.variables[*left as usize].clone(),
+            right: if right.is_empty() {
+                vec![Symbol::Epsilon]
+            } else {
+                right
+                    .iter()
+                    .map(|s| match *s {
+                        SymbolId::Terminal(t) => self.get_terminal_symbol(t),
+                        SymbolId::Variable(v) => {
+                            Symbol::Variable(self.variables[v as usize].clone())
+                        }
+                    })
+                    .collect()
+            },
+            action: self.actions[p],
+        }
+    }
+
+    /// Fills the table, reporting a conflict for every production that
+    /// finds its cell taken, as `CFG::get_table` does.
+    fn compute_table(&mut self) -> Result<(), Vec<Conflict>> {
+        let columns = self.terminals.len() + 1;
+        let mut table = vec![NO_RULE; self.variables.len() * columns];
+        let mut from_follow = BitSet::new(table.len());
+        let mut conflicts = Vec::new();
+        for (p, (left, right)) in self.rules.iter().enumerate() {
+            let (first, nullable) = self.get_string_first(right);
+            let mut follow = BitSet::new(columns);
+            if nullable {
+                follow.union_with(&self.follows[*left as usize]);
+            }
+            let entries = first.iter().map(|t| (t, false)).chain(
+                follow
+                    .iter()
+                    .filter(|t| !first.contains(*t))
+                    .map(|t| (t, true)),
+            );
+            for (t, follow) in entries {
+                let i = *left as usize * columns + t;
+                if table[i] == NO_RULE {
+                    table[i] = p as u32;
+                    if follow {
+                        from_follow.insert(i);
+                    }
+                } else {
+                    conflicts.push(Conflict {
+                        variable: self.variables[*left as usize].clone(),
+                        lookahead: self.get_terminal_symbol(t as u32),
+                        productions: (
+                            self.get_production(table[i] as usize),
+                            self.get_production(p),
+                        ),
+                        kind: if follow || from_follow.contains(i) {
+                            ConflictKind::FirstFollow
+                        } else {
+                            ConflictKind::FirstFirst
+                        },
+                    });
+                }
+            }
+        }
+        self.table = table;
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    pub fn get_cell(&self, var: u32, terminal: u32) -> Option<usize> {
+        match self.table[var as usize * (self.terminals.len() + 1) + terminal as usize] {
+            NO_RULE => None,
+            p => Some(p as usize),
+        }
+    }
+
+    /// Parses `tokens` and returns the productions of the leftmost
+    /// derivation, as indices into `rules`.
+    pub fn parse_derivation(&self, tokens: &[T]) -> Result<Vec<usize>, ParseError> {
+        let mut stack = vec![SymbolId::Variable(self.start)];
+        let mut derivation = Vec::new();
+        let mut pos = 0;
+        let mut a = self.get_token(tokens, pos)?;
+        while let Some(top) = stack.pop() {
+            match top {
+                SymbolId::Terminal(t) => {
+                    if t != a {
+                        return Err(self.error(pos, a, vec![t]));
+                    }
+                    pos += 1;
+                    a = self.get_token(tokens, pos)?;
+                }
+                SymbolId::Variable(v) => match self.get_cell(v, a) {
+                    Some(p) => {
+                        derivation.push(p);
+                        stack.extend(self.rules[p].1.iter().rev());
+                    }
+                    None => {
+                        let expected = (0..=self.end())
+                            .filter(|t| self.get_cell(v, *t).is_some())
+                            .collect();
+                        return Err(self.error(pos, a, expected));
+                    }
+                },
+            }
+        }
+        if a != self.end() {
+            return Err(self.error(pos, a, vec![self.end()]));
+        }
+        Ok(derivation)
+    }
+
+    /// Parses `tokens` into the same tree as `CFG::parse`.
+    pub fn parse<'a>(&self, tokens: &'a [T]) -> Result<Tree<'a, T>, ParseError> {
+        let derivation = self.parse_derivation(tokens)?;
+        let mut out = Vec::new();
+        let mut stack = vec![SymbolId::Variable(self.start)];
+        let mut pos = 0;
+        let mut derivation = derivation.into_iter();
+        while let Some(top) = stack.pop() {
+            match top {
+                SymbolId::Terminal(_) => {
+                    out.push(TempNode::Val(pos, &tokens[pos]));
+                    out.push(TempNode::End);
+                    pos += 1;
+                }
+                SymbolId::Variable(_) => {
+                    let p = derivation.next().unwrap();
+                    let right = &self.rules[p].1;
+                    out.push(TempNode::Rule(p));
+                    if right.is_empty() {
+                        out.push(TempNode::Eps);
+                    }
+                    for s in right.iter() {
+                        out.push(match *s {
+                            SymbolId::Terminal(t) => {
+                                TempNode::Term(self.terminals[t as usize].clone())
+                            }
+                            SymbolId::Variable(v) => {
+                                TempNode::Var(self.variables[v as usize].clone())
+                            }
+                        });
+                    }
+                    out.push(TempNode::End);
+                    stack.extend(right.iter().rev());
+                }
+            }
+        }
+        Ok(build_root(&self.variables[self.start as usize], out))
+    }
+
+    fn get_token(&self, tokens: &[T], pos: usize) -> Result<u32, ParseError> {
+        match tokens.get(pos) {
+            #[allow(enum_intrinsics_non_enums)]
+            Some(v) => self
+                .discriminant2terminal
+                .get(&discriminant(v))
+                .copied()
+                .ok_or(ParseError::UnknownToken { index: pos }),
+            None => Ok(self.end()),
+        }
+    }
+
+    fn error(&self, index: usize, found: u32, expected: Vec<u32>) -> ParseError {
+        ParseError::UnexpectedToken {
+            index,
+            found: self.get_terminal_symbol(found),
+            expected: expected
+                .into_iter()
+                .map(|t| self.get_terminal_symbol(t))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_bitset() {
+    let mut s = BitSet::new(130);
+    assert!(s.insert(3));
+    assert!(s.insert(129));
+    assert!(!s.insert(3));
+    assert!(s.contains(129) && !s.contains(64));
+    let mut t = BitSet::new(130);
+    t.insert(64);
+    assert!(s.union_with(&t));
+    assert!(!s.union_with(&t));
+    assert_eq!(s.iter().collect::<Vec<_>>(), vec![3, 64, 129]);
+}
+
+#[test]
+fn test_interned_parse() {
+    use crate::fixtures::{arith_grammar, Token::*};
+    let c = arith_grammar();
+    let i = c.intern().unwrap();
+    let follows = c.get_follows();
+    for (v, name) in i.variables.iter().enumerate() {
+        let mut follow: Vec<_> = i.follows[v]
+            .iter()
+            .map(|t| i.get_terminal_symbol(t as u32))
+            .collect();
+        let mut expected: Vec<_> = follows.get(name).unwrap().iter().cloned().collect();
+        follow.sort();
+        expected.sort();
+        assert_eq!(follow, expected);
+    }
+    assert!(i.nullable.contains(1) && !i.nullable.contains(0));
+
+    let tokens = [LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)];
+    assert_eq!(
+        i.parse_derivation(&tokens).unwrap(),
+        vec![0, 3, 6, 0, 3, 7, 5, 1, 3, 7, 5, 2, 4, 7, 5, 2]
+    );
+    assert_eq!(
+        format!("{:?}", i.parse(&tokens).unwrap()),
+        format!("{:?}", c.parse(&tokens).unwrap())
+    );
+    for tokens in [
+        &[Num(1), Num(2)][..],
+        &[LeftBracket, Num(1)],
+        &[RightBracket],
+    ] {
+        assert_eq!(i.parse(tokens).unwrap_err(), c.parse(tokens).unwrap_err());
+    }
+
+    assert_eq!(i.get_production(2).to_string(), "A => #");
+
+    let mut not_ll1 = c;
+    not_ll1.rules.push(Production {
+        left: "E".into(),
+        right: vec![Symbol::Variable("E".into())],
+        action: None,
+    });
+    not_ll1.rules.push(Production {
+        left: "B".into(),
+        right: vec![Symbol::Terminal("a".into())],
+        action: None,
+    });
+    let by_lookahead = |mut conflicts: Vec<Conflict>| {
+        conflicts.sort_by_key(|c| (c.variable.clone(), c.lookahead.clone()));
+        conflicts
+    };
+    let conflicts = by_lookahead(not_ll1.intern().unwrap_err());
+    assert_eq!(conflicts, by_lookahead(not_ll1.get_table().unwrap_err()));
+    assert_eq!(
+        conflicts.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+        vec![
+            "FIRST/FOLLOW conflict at ( B, a ): B => # | B => a",
+            "FIRST/FIRST conflict at ( E, d ): E => T A | E => E",
+            "FIRST/FIRST conflict at ( E, l ): E => T A | E => E",
+        ]
+    );
+}
+
+#[test]
+fn test_intern_undefined_variables() {
+    use crate::fixtures::ListToken::*;
+    let c = context_free_grammar!(
+        terminals: {
+            id = Id
+        }
+        rules: {
+            S => id U;
+        }
+        start: S
+    );
+    let i = c.intern().unwrap();
+    assert_eq!(i.variables, vec!["S", "U"]);
+    for tokens in [&[][..], &[Id], &[Id, Id]] {
+        assert_eq!(i.parse(tokens).unwrap_err(), c.parse(tokens).unwrap_err());
+    }
+
+    let mut c = c;
+    c.start = "T".into();
+    let i = c.intern().unwrap();
+    assert_eq!(i.variables, vec!["S", "T", "U"]);
+    assert_eq!(i.parse(&[Id]).unwrap_err(), c.parse(&[Id]).unwrap_err());
+}
@@ -0,0 +1,15 @@
+//! LL(1), LR, Earley and GLL parsing of context-free grammars, along with
+//! the grammar transformations and the code generators built on them. The
+//! grammars are written with `context_free_grammar!` or read from BNF text
+//! with `CFG::from_bnf`.
+
+pub mod bnf;
+pub mod cfg;
+pub mod codegen;
+pub mod cst;
+pub mod earley;
+#[cfg(test)]
+mod fixtures;
+pub mod gll;
+pub mod interned;
+pub mod lr;
@@ -0,0 +1,967 @@
+use crate::cfg::{write_grid, Assoc, ParseError, Production, Sets, Symbol, Tree, CFG};
+#[cfg(test)]
+use crate::context_free_grammar;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+
+/// An LR(0) item: production `production` of the augmented grammar with the
+/// dot before its `dot`-th symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Item {
+    pub production: usize,
+    pub dot: usize,
+}
+
+/// An LR(1) item, an LR(0) item with the terminal (or `$`) that may follow
+/// once it is reduced.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lr1Item {
+    pub item: Item,
+    pub lookahead: Symbol,
+}
+
+pub trait LrItem: Clone + Ord + Hash {
+    fn core(&self) -> Item;
+    fn advance(&self) -> Self;
+    fn lookahead(&self) -> Option<&Symbol> {
+        None
+    }
+}
+
+impl LrItem for Item {
+    fn core(&self) -> Item {
+        *self
+    }
+
+    fn advance(&self) -> Self {
+        Item {
+            production: self.production,
+            dot: self.dot + 1,
+        }
+    }
+}
+
+impl LrItem for Lr1Item {
+    fn core(&self) -> Item {
+        self.item
+    }
+
+    fn advance(&self) -> Self {
+        Lr1Item {
+            item: self.item.advance(),
+            lookahead: self.lookahead.clone(),
+        }
+    }
+
+    fn lookahead(&self) -> Option<&Symbol> {
+        Some(&self.lookahead)
+    }
+}
+
+/// A collection of LR item sets. `rules` is the augmented grammar, whose
+/// production 0 is `S' => S`, and `states[0]` is the closure of `S' => . S`
+/// (with lookahead `$` for LR(1) items).
+#[derive(Debug)]
+pub struct Automaton<I = Item> {
+    pub rules: Vec<Production>,
+    pub states: Vec<Vec<I>>,
+    pub transitions: HashMap<(usize, Symbol), usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+}
+
+/// How precedence settles a shift/reduce conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    Shift,
+    Reduce,
+    /// Neither, the cell is left empty (`%nonassoc`).
+    Error,
+    /// The rule or the lookahead has no precedence.
+    Unresolved,
+}
+
+// Named after the two actions in a conflict, as yacc reports them.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LrConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+    /// Reducing by a production where `S' => S .` accepts, which happens
+    /// when the start variable derives itself.
+    AcceptReduce,
+}
+
+/// Two actions competing for the same cell of the ACTION table, with the
+/// items of the state that call for them.
+#[derive(Debug)]
+pub struct LrConflict {
+    pub state: usize,
+    pub lookahead: Symbol,
+    pub actions: (Action, Action),
+    pub kind: LrConflictKind,
+    pub items: Vec<String>,
+}
+
+/// ACTION and GOTO tables. `Reduce(p)` refers to `rules[p]`, the augmented
+/// grammar the table was built from.
+#[derive(Debug)]
+pub struct LrTable {
+    pub rules: Vec<Production>,
+    pub action: HashMap<(usize, Symbol), Action>,
+    pub goto: HashMap<(usize, String), usize>,
+    pub states: usize,
+    pub terminals: Vec<Symbol>,
+    pub variables: Vec<String>,
+}
+
+/// The right side of `p` without `Epsilon`, an ε-production has no symbols.
+pub(crate) fn body(p: &Production) -> &[Symbol] {
+    if p.right.iter().all(|s| *s == Symbol::Epsilon) {
+        &[]
+    } else {
+        &p.right
+    }
+}
+
+impl<I: LrItem> Automaton<I> {
+    /// Explores the item sets reachable from `start`, `closure` completing
+    /// every kernel.
+    fn build<F>(rules: Vec<Production>, start: I, closure: F) -> Automaton<I>
+    where
+        F: Fn(&Automaton<I>, Vec<I>) -> Vec<I>,
+    {
+        let mut automaton = Automaton {
+            rules,
+            states: Vec::new(),
+            transitions: HashMap::new(),
+        };
+        let start = closure(&automaton, vec![start]);
+        let mut index = HashMap::new();
+        index.insert(start.clone(), 0);
+        automaton.states.push(start);
+        let mut i = 0;
+        while i < automaton.states.len() {
+            let mut symbols: Vec<_> = automaton.states[i]
+                .iter()
+                .filter_map(|item| automaton.next_symbol(item))
+                .cloned()
+                .collect();
+            symbols.sort();
+            symbols.dedup();
+            for x in symbols {
+                let kernel = automaton.states[i]
+                    .iter()
+                    .filter(|item| automaton.next_symbol(item) == Some(&x))
+                    .map(|item| item.advance())
+                    .collect();
+                let next = closure(&automaton, kernel);
+                let states = &mut automaton.states;
+                let n = *index.entry(next.clone()).or_insert_with(|| {
+                    states.push(next);
+                    states.len() - 1
+                });
+                automaton.transitions.insert((i, x), n);
+            }
+            i += 1;
+        }
+        automaton
+    }
+
+    /// The symbol after the dot, `None` for a complete item.
+    pub fn next_symbol(&self, item: &I) -> Option<&Symbol> {
+        let core = item.core();
+        body(&self.rules[core.production]).get(core.dot)
+    }
+
+    /// The complete items of every state, with their state.
+    fn get_complete_items(&self) -> Vec<(usize, &I)> {
+        self.states
+            .iter()
+            .enumerate()
+            .flat_map(|(i, items)| {
+                items
+                    .iter()
+                    .filter(|item| self.next_symbol(item).is_none())
+                    .map(move |item| (i, item))
+            })
+            .collect()
+    }
+
+    /// The items of `state` calling for `action` on `lookahead`.
+    fn get_items_for(&self, state: usize, action: &Action, lookahead: &Symbol) -> Vec<String> {
+        self.states[state]
+            .iter()
+            .filter(|item| match action {
+                Action::Shift(_) => self.next_symbol(item) == Some(lookahead),
+                Action::Reduce(p) => self.reduces(item, *p, lookahead),
+                Action::Accept => self.reduces(item, 0, lookahead),
+            })
+            .map(|item| self.format_item(item))
+            .collect()
+    }
+
+    fn reduces(&self, item: &I, production: usize, lookahead: &Symbol) -> bool {
+        item.core().production == production
+            && self.next_symbol(item).is_none()
+            && match item.lookahead() {
+                Some(a) => a == lookahead,
+                None => true,
+            }
+    }
+
+    pub fn format_item(&self, item: &I) -> String {
+        let core = item.core();
+        let rule = &self.rules[core.production];
+        let mut right: Vec<_> = body(rule).iter().map(|s| s.to_string()).collect();
+        right.insert(core.dot, ".".into());
+        match item.lookahead() {
+            Some(a) => format!("{} => {}, {}", rule.left, right.join(" "), a),
+            None => format!("{} => {}", rule.left, right.join(" ")),
+        }
+    }
+}
+
+impl Automaton<Item> {
+    pub fn closure(&self, kernel: Vec<Item>) -> Vec<Item> {
+        let mut items = kernel;
+        let mut i = 0;
+        while i < items.len() {
+            if let Some(Symbol::Variable(v)) = self.next_symbol(&items[i]) {
+                for (j, p) in self.rules.iter().enumerate() {
+                    let item = Item {
+                        production: j,
+                        dot: 0,
+                    };
+                    if p.left == *v && !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            i += 1;
+        }
+        items.sort();
+        items
+    }
+}
+
+impl Automaton<Lr1Item> {
+    /// Adds `[B => . γ, b]` for every `[A => α . B β, a]` and every `b` in
+    /// FIRST(β a), with `firsts` the FIRST sets of the grammar.
+    pub fn closure(&self, firsts: &Sets, kernel: Vec<Lr1Item>) -> Vec<Lr1Item> {
+        let mut items = kernel;
+        let mut i = 0;
+        while i < items.len() {
+            let item = items[i].clone();
+            if let Some(Symbol::Variable(v)) = self.next_symbol(&item) {
+                let mut rest =
+                    body(&self.rules[item.item.production])[item.item.dot + 1..].to_vec();
+                rest.push(item.lookahead.clone());
+                let mut lookaheads: Vec<_> = firsts.get_string_first(&rest).into_iter().collect();
+                lookaheads.sort();
+                for (j, p) in self.rules.iter().enumerate() {
+                    if p.left != *v {
+                        continue;
+                    }
+                    for b in lookaheads.iter() {
+                        let new = Lr1Item {
+                            item: Item {
+                                production: j,
+                                dot: 0,
+                            },
+                            lookahead: b.clone(),
+                        };
+                        if !items.contains(&new) {
+                            items.push(new);
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+        items.sort();
+        items
+    }
+
+    /// Merges the states with the same LR(0) core, keeping the order in which
+    /// the cores first appear.
+    pub fn merge_cores(&self) -> Automaton<Lr1Item> {
+        let mut cores: Vec<Vec<Item>> = Vec::new();
+        let mut states: Vec<Vec<Lr1Item>> = Vec::new();
+        let mut merged = Vec::new();
+        for items in self.states.iter() {
+            let mut core: Vec<_> = items.iter().map(|i| i.item).collect();
+            core.dedup();
+            let n = match cores.iter().position(|c| *c == core) {
+                Some(n) => n,
+                None => {
+                    cores.push(core);
+                    states.push(Vec::new());
+                    cores.len() - 1
+                }
+            };
+            states[n].extend(items.iter().cloned());
+            merged.push(n);
+        }
+        for items in states.iter_mut() {
+            items.sort();
+            items.dedup();
+        }
+        Automaton {
+            rules: self.rules.clone(),
+            states,
+            transitions: self
+                .transitions
+                .iter()
+                .map(|((i, x), n)| ((merged[*i], x.clone()), merged[*n]))
+                .collect(),
+        }
+    }
+}
+
+impl<T, V> CFG<T, V> {
+    /// The productions with a fresh start variable `S'` and `S' => S` as
+    /// production 0.
+    pub fn get_augmented_rules(&self) -> Vec<Production> {
+        std::iter::once(Production {
+            left: self.new_var(&self.start),
+            right: vec![Symbol::Variable(self.start.clone())],
+            action: None,
+        })
+        .chain(self.rules.iter().cloned())
+        .collect()
+    }
+
+    pub fn get_lr0_automaton(&self) -> Automaton {
+        let start = Item {
+            production: 0,
+            dot: 0,
+        };
+        Automaton::build(self.get_augmented_rules(), start, |a, kernel| {
+            a.closure(kernel)
+        })
+    }
+
+    pub fn get_lr1_automaton(&self) -> Automaton<Lr1Item> {
+        let firsts = self.get_firsts();
+        let start = Lr1Item {
+            item: Item {
+                production: 0,
+                dot: 0,
+            },
+            lookahead: Symbol::EndOfInput,
+        };
+        Automaton::build(self.get_augmented_rules(), start, |a, kernel| {
+            a.closure(&firsts, kernel)
+        })
+    }
+
+    /// The LR(1) automaton with the states of equal cores merged.
+    pub fn get_lalr_automaton(&self) -> Automaton<Lr1Item> {
+        self.get_lr1_automaton().merge_cores()
+    }
+
+    /// The LR(0) table, reducing complete items on every lookahead.
+    pub fn get_lr0_table(&self) -> Result<LrTable, Vec<LrConflict>> {
+        let automaton = self.get_lr0_automaton();
+        let terminals = self.get_lr_terminals();
+        let reductions = automaton
+            .get_complete_items()
+            .into_iter()
+            .flat_map(|(i, item)| {
+                terminals
+                    .iter()
+                    .map(move |a| (i, item.production, a.clone()))
+            })
+            .collect();
+        self.build_lr_table(automaton, reductions)
+    }
+
+    /// The SLR(1) table, reducing `A => β .` only on FOLLOW(A).
+    pub fn get_slr_table(&self) -> Result<LrTable, Vec<LrConflict>> {
+        let automaton = self.get_lr0_automaton();
+        let follows = self.get_follows();
+        let mut reductions = Vec::new();
+        for (i, item) in automaton.get_complete_items() {
+            let p = item.production;
+            if p == 0 {
+                reductions.push((i, p, Symbol::EndOfInput));
+                continue;
+            }
+            if let Some(follow) = follows.get(&automaton.rules[p].left) {
+                let mut follow: Vec<_> = follow.iter().cloned().collect();
+                follow.sort();
+                reductions.extend(follow.into_iter().map(|a| (i, p, a)));
+            }
+        }
+        self.build_lr_table(automaton, reductions)
+    }
+
+    /// The canonical LR(1) table, reducing `[A => β ., a]` on `a`.
+    pub fn get_lr1_table(&self) -> Result<LrTable, Vec<LrConflict>> {
+        self.build_lr1_table(self.get_lr1_automaton())
+    }
+
+    pub fn get_lalr_table(&self) -> Result<LrTable, Vec<LrConflict>> {
+        self.build_lr1_table(self.get_lalr_automaton())
+    }
+
+    fn build_lr1_table(&self, automaton: Automaton<Lr1Item>) -> Result<LrTable, Vec<LrConflict>> {
+        let reductions = automaton
+            .get_complete_items()
+            .into_iter()
+            .map(|(i, item)| (i, item.item.production, item.lookahead.clone()))
+            .collect();
+        self.build_lr_table(automaton, reductions)
+    }
+
+    fn get_lr_terminals(&self) -> Vec<Symbol> {
+        self.terminals
+            .iter()
+            .map(|t| Symbol::Terminal(t.clone()))
+            .chain(std::iter::once(Symbol::EndOfInput))
+            .collect()
+    }
+
+    /// Fills ACTION and GOTO from the transitions of an item set automaton and
+    /// the `(state, production, lookahead)` reductions. The first action of a
+    /// cell wins and every other one is reported as a conflict.
+    fn build_lr_table<I: LrItem>(
+        &self,
+        automaton: Automaton<I>,
+        reductions: Vec<(usize, usize, Symbol)>,
+    ) -> Result<LrTable, Vec<LrConflict>> {
+        let mut action = HashMap::new();
+        let mut goto = HashMap::new();
+        let mut shifts: Vec<_> = automaton.transitions.iter().collect();
+        shifts.sort();
+        for ((i, x), n) in shifts {
+            match x {
+                Symbol::Variable(v) => {
+                    goto.insert((*i, v.clone()), *n);
+                }
+                _ => {
+                    action.insert((*i, x.clone()), Action::Shift(*n));
+                }
+            }
+        }
+        let mut conflicts = Vec::new();
+        // Cells emptied by %nonassoc, which stay errors.
+        let mut errors = HashSet::new();
+        for (i, p, a) in reductions {
+            let new = if p == 0 {
+                if a != Symbol::EndOfInput {
+                    continue;
+                }
+                Action::Accept
+            } else {
+                Action::Reduce(p)
+            };
+            let key = (i, a.clone());
+            if errors.contains(&key) {
+                continue;
+            }
+            match action.get(&key) {
+                None => {
+                    action.insert(key, new);
+                }
+                Some(old) if *old == new => {}
+                Some(Action::Shift(n)) if p != 0 => {
+                    let shift = Action::Shift(*n);
+                    match self.resolve_by_precedence(&automaton.rules[p], &a) {
+                        Resolution::Shift => {}
+                        Resolution::Reduce => {
+                            action.insert(key, new);
+                        }
+                        Resolution::Error => {
+                            action.remove(&key);
+                            errors.insert(key);
+                        }
+                        Resolution::Unresolved => {
+                            let mut items = automaton.get_items_for(i, &shift, &a);
+                            items.extend(automaton.get_items_for(i, &new, &a));
+                            conflicts.push(LrConflict {
+                                state: i,
+                                actions: (shift, new),
+                                kind: LrConflictKind::ShiftReduce,
+                                lookahead: a,
+                                items,
+                            })
+                        }
+                    }
+                }
+                Some(old) => {
+                    let mut items = automaton.get_items_for(i, old, &a);
+                    items.extend(automaton.get_items_for(i, &new, &a));
+                    conflicts.push(LrConflict {
+                        state: i,
+                        actions: (*old, new),
+                        kind: match (old, new) {
+                            (Action::Shift(_), _) => LrConflictKind::ShiftReduce,
+                            (Action::Accept, _) | (_, Action::Accept) => {
+                                LrConflictKind::AcceptReduce
+                            }
+                            _ => LrConflictKind::ReduceReduce,
+                        },
+                        lookahead: a,
+                        items,
+                    })
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            Ok(LrTable {
+                rules: automaton.rules,
+                action,
+                goto,
+                states: automaton.states.len(),
+                terminals: self.get_lr_terminals(),
+                variables: self.get_variables(),
+            })
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    /// Settles a shift/reduce conflict between shifting `a` and reducing by
+    /// `rule` the way yacc does: the higher precedence wins and on a tie the
+    /// associativity of `a` decides.
+    fn resolve_by_precedence(&self, rule: &Production, a: &Symbol) -> Resolution {
+        let rule_level = match self.get_production_precedence(rule) {
+            Some((level, _)) => level,
+            None => return Resolution::Unresolved,
+        };
+        let (level, assoc) = match a {
+            Symbol::Terminal(t) => match self.precedence.get(t) {
+                Some(p) => *p,
+                None => return Resolution::Unresolved,
+            },
+            _ => return Resolution::Unresolved,
+        };
+        match rule_level.cmp(&level) {
+            Ordering::Greater => Resolution::Reduce,
+            Ordering::Less => Resolution::Shift,
+            Ordering::Equal => match assoc {
+                Assoc::Left => Resolution::Reduce,
+                Assoc::Right => Resolution::Shift,
+                Assoc::Nonassoc => Resolution::Error,
+            },
+        }
+    }
+
+    /// The shift-reduce driver. Builds the same `Tree` as `parse`, with
+    /// terminals as leaves and ε-productions holding a single `Epsilon`.
+    pub fn parse_lr<'a>(
+        &self,
+        table: &LrTable,
+        tokens: &'a [T],
+    ) -> Result<Tree<'a, T>, ParseError> {
+        let mut states = vec![0];
+        let mut trees: Vec<Tree<'a, T>> = Vec::new();
+        let mut pos = 0;
+        loop {
+            let a = self
+                .get_terminal(tokens, pos)
+                .ok_or(ParseError::UnknownToken { index: pos })?;
+            let state = *states.last().unwrap();
+            match table.action.get(&(state, a.clone())) {
+                Some(Action::Shift(n)) => {
+                    trees.push(Tree::Leaf {
+                        index: pos,
+                        token: &tokens[pos],
+                    });
+                    states.push(*n);
+                    pos += 1;
+                }
+                Some(Action::Reduce(p)) => {
+                    let rule = &table.rules[*p];
+                    let n = body(rule).len();
+                    let nodes = if n == 0 {
+                        vec![Tree::Epsilon]
+                    } else {
+                        trees.split_off(trees.len() - n)
+                    };
+                    states.truncate(states.len() - n);
+                    let top = *states.last().unwrap();
+                    states.push(table.goto[&(top, rule.left.clone())]);
+                    // `rules[0]` is the augmented start, reduced by `Accept`.
+                    trees.push(Tree::Node {
+                        name: rule.left.clone(),
+                        production: Some(p - 1),
+                        nodes,
+                    });
+                }
+                Some(Action::Accept) => return Ok(trees.pop().unwrap()),
+                None => {
+                    return Err(ParseError::UnexpectedToken {
+                        index: pos,
+                        found: a,
+                        expected: table.get_expected(state),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl LrTable {
+    /// The terminals with an action in `state`.
+    pub fn get_expected(&self, state: usize) -> Vec<Symbol> {
+        self.terminals
+            .iter()
+            .filter(|t| self.action.contains_key(&(state, (*t).clone())))
+            .cloned()
+            .collect()
+    }
+
+    /// The table as rows of cells, headed by the terminals then the variables.
+    pub fn get_grid(&self) -> Vec<Vec<String>> {
+        let mut grid = vec![std::iter::once("State".to_string())
+            .chain(self.terminals.iter().map(|t| t.to_string()))
+            .chain(self.variables.iter().cloned())
+            .collect::<Vec<_>>()];
+        for i in 0..self.states {
+            grid.push(
+                std::iter::once(i.to_string())
+                    .chain(
+                        self.terminals
+                            .iter()
+                            .map(|t| match self.action.get(&(i, t.clone())) {
+                                Some(a) => a.to_string(),
+                                None => String::new(),
+                            }),
+                    )
+                    .chain(
+                        self.variables
+                            .iter()
+                            .map(|v| match self.goto.get(&(i, v.clone())) {
+                                Some(n) => n.to_string(),
+                                None => String::new(),
+                            }),
+                    )
+                    .collect(),
+            );
+        }
+        grid
+    }
+}
+
+impl<I: LrItem> Display for Automaton<I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut transitions: Vec<_> = self.transitions.iter().collect();
+        transitions.sort();
+        for (i, items) in self.states.iter().enumerate() {
+            writeln!(f, "I{}:", i)?;
+            for item in items {
+                writeln!(f, "  {}", self.format_item(item))?;
+            }
+            for ((_, x), n) in transitions.iter().filter(|((s, _), _)| *s == i) {
+                writeln!(f, "  goto({}) = I{}", x, n)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Shift(n) => write!(f, "s{}", n),
+            Action::Reduce(p) => write!(f, "r{}", p),
+            Action::Accept => write!(f, "acc"),
+        }
+    }
+}
+
+impl Display for LrTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, rule) in self.rules.iter().enumerate() {
+            writeln!(f, "{}: {}", i, rule)?;
+        }
+        writeln!(f)?;
+        write_grid(f, &self.get_grid())
+    }
+}
+
+impl Display for LrConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} conflict in state {} on {}: {} | {} ({})",
+            match self.kind {
+                LrConflictKind::ShiftReduce => "shift/reduce",
+                LrConflictKind::ReduceReduce => "reduce/reduce",
+                LrConflictKind::AcceptReduce => "accept/reduce",
+            },
+            self.state,
+            self.lookahead,
+            self.actions.0,
+            self.actions.1,
+            self.items.join("; ")
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn format_tree<T: std::fmt::Debug>(tree: &Tree<'_, T>) -> String {
+    match tree {
+        Tree::Epsilon => "#".into(),
+        Tree::Leaf { token, .. } => format!("{:?}", token),
+        Tree::Node { name, nodes, .. } => format!(
+            "{}({})",
+            name,
+            nodes.iter().map(format_tree).collect::<Vec<_>>().join(" ")
+        ),
+    }
+}
+
+#[test]
+fn test_slr_table() {
+    use crate::fixtures::Token::{Add, LeftBracket, Mul, Num, RightBracket};
+    let c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            l = LeftBracket,
+            r = RightBracket,
+            d = Num(0)
+        }
+        rules: {
+            E => E a T | T;
+            T => T m F | F;
+            F => l E r | d;
+        }
+        start: E
+    );
+    let automaton = c.get_lr0_automaton();
+    println!("{}", automaton);
+    assert_eq!(12, automaton.states.len());
+    assert_eq!("E' => E", automaton.rules[0].to_string());
+    assert_eq!(
+        "E => E . a T",
+        automaton.format_item(&Item {
+            production: 1,
+            dot: 1
+        })
+    );
+
+    let conflicts = c.get_lr0_table().unwrap_err();
+    assert!(conflicts
+        .iter()
+        .all(|c| c.kind == LrConflictKind::ShiftReduce));
+    assert!(conflicts.iter().any(|c| c.to_string()
+        == "shift/reduce conflict in state 3 on m: s7 | r2 (T => T . m F; E => T .)"));
+
+    let table = c.get_slr_table().unwrap();
+    println!("{}", table);
+    let tree = c
+        .parse_lr(
+            &table,
+            &[Num(1), Add, Num(2), Mul, LeftBracket, Num(3), RightBracket],
+        )
+        .unwrap();
+    assert_eq!(
+        format_tree(&tree),
+        "E(E(T(F(Num(1)))) Add T(T(F(Num(2))) Mul F(LeftBracket E(T(F(Num(3)))) RightBracket)))"
+    );
+    assert_eq!(
+        c.parse_lr(&table, &[Num(1), Add, Mul])
+            .unwrap_err()
+            .to_string(),
+        "expected one of `l`, `d` but found `m` at token 2"
+    );
+    assert_eq!(
+        c.parse_lr(&table, &[LeftBracket, Num(1)])
+            .unwrap_err()
+            .to_string(),
+        "expected one of `a`, `r` but found `$` at token 2"
+    );
+}
+
+#[test]
+fn test_slr_epsilon() {
+    use crate::fixtures::{list_grammar, ListToken::*};
+    let c = list_grammar();
+    let table = c.get_slr_table().unwrap();
+    let tree = c.parse_lr(&table, &[Id, Comma, Id]).unwrap();
+    assert_eq!(format_tree(&tree), "L(Id R(Comma Id R(#)))");
+    assert_eq!(
+        format_tree(&tree),
+        format_tree(&c.parse(&[Id, Comma, Id]).unwrap())
+    );
+}
+
+#[test]
+fn test_lalr_table() {
+    #[derive(Debug)]
+    enum Token {
+        Assign,
+        Deref,
+        Id,
+    }
+    use Token::{Assign, Deref, Id};
+    let c = context_free_grammar!(
+        terminals: {
+            e = Assign,
+            s = Deref,
+            i = Id
+        }
+        rules: {
+            S => L e R | R;
+            L => s R | i;
+            R => L;
+        }
+        start: S
+    );
+    let conflicts = c.get_slr_table().unwrap_err();
+    assert_eq!(1, conflicts.len());
+    assert_eq!(LrConflictKind::ShiftReduce, conflicts[0].kind);
+    assert_eq!(
+        conflicts[0].items,
+        vec!["S => L . e R".to_string(), "R => L .".to_string()]
+    );
+
+    assert_eq!(14, c.get_lr1_automaton().states.len());
+    let automaton = c.get_lalr_automaton();
+    println!("{}", automaton);
+    assert_eq!(10, automaton.states.len());
+    assert!(c.get_lr1_table().is_ok());
+    let table = c.get_lalr_table().unwrap();
+    println!("{}", table);
+    let tree = c.parse_lr(&table, &[Deref, Id, Assign, Id]).unwrap();
+    assert_eq!(format_tree(&tree), "S(L(Deref R(L(Id))) Assign R(L(Id)))");
+}
+
+#[test]
+fn test_lalr_conflict() {
+    #[derive(Debug)]
+    enum Token {
+        A,
+        B,
+        C,
+        D,
+        E,
+    }
+    let c = context_free_grammar!(
+        terminals: {
+            a = Token::A,
+            b = Token::B,
+            c = Token::C,
+            d = Token::D,
+            e = Token::E
+        }
+        rules: {
+            S => a X d | b Y d | a Y e | b X e;
+            X => c;
+            Y => c;
+        }
+        start: S
+    );
+    let table = c.get_lr1_table().unwrap();
+    assert!(c.parse_lr(&table, &[Token::B, Token::C, Token::E]).is_ok());
+    let conflicts = c.get_lalr_table().unwrap_err();
+    assert_eq!(2, conflicts.len());
+    assert!(conflicts
+        .iter()
+        .all(|c| c.kind == LrConflictKind::ReduceReduce));
+    assert!(conflicts[0]
+        .to_string()
+        .starts_with("reduce/reduce conflict in state"));
+    assert_eq!(conflicts[0].items, vec!["X => c ., d", "Y => c ., d"]);
+}
+
+#[test]
+fn test_accept_reduce_conflict() {
+    #[derive(Debug)]
+    enum Token {
+        A,
+    }
+    let c = context_free_grammar!(
+        terminals: {
+            a = Token::A
+        }
+        rules: {
+            S => S | a;
+        }
+        start: S
+    );
+    let conflicts = c.get_slr_table().unwrap_err();
+    assert_eq!(1, conflicts.len());
+    assert_eq!(LrConflictKind::AcceptReduce, conflicts[0].kind);
+    assert!(conflicts[0]
+        .to_string()
+        .starts_with("accept/reduce conflict in state"));
+}
+
+#[test]
+fn test_precedence() {
+    use crate::bnf::UnknownTerminal;
+    use crate::fixtures::{Token, Token::*};
+    let c = context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            p = Pow,
+            n = Less,
+            l = LeftBracket,
+            r = RightBracket,
+            d = Num(0)
+        }
+        precedence: {
+            nonassoc n;
+            left a;
+            left m;
+            right p;
+        }
+        rules: {
+            E => E a E | E m E | E p E | E n E | l E r | d;
+        }
+        start: E
+    );
+    assert_eq!(c.precedence["n"], (0, Assoc::Nonassoc));
+    assert_eq!(c.precedence["p"], (3, Assoc::Right));
+    for table in [c.get_slr_table().unwrap(), c.get_lalr_table().unwrap()] {
+        let parse = |tokens: &[Token]| format_tree(&c.parse_lr(&table, tokens).unwrap());
+        assert_eq!(
+            parse(&[Num(1), Add, Num(2), Mul, Num(3)]),
+            "E(E(Num(1)) Add E(E(Num(2)) Mul E(Num(3))))"
+        );
+        assert_eq!(
+            parse(&[Num(1), Add, Num(2), Add, Num(3)]),
+            "E(E(E(Num(1)) Add E(Num(2))) Add E(Num(3)))"
+        );
+        assert_eq!(
+            parse(&[Num(1), Pow, Num(2), Pow, Num(3)]),
+            "E(E(Num(1)) Pow E(E(Num(2)) Pow E(Num(3))))"
+        );
+        assert_eq!(
+            parse(&[LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)]),
+            "E(E(LeftBracket E(E(Num(1)) Add E(Num(2))) RightBracket) Mul E(Num(3)))"
+        );
+        assert_eq!(
+            c.parse_lr(&table, &[Num(1), Less, Num(2), Less, Num(3)])
+                .unwrap_err()
+                .index(),
+            Some(3)
+        );
+    }
+
+    let mut c = c;
+    assert_eq!(
+        c.add_precedence(Assoc::Left, &["a", "x"]),
+        Err(UnknownTerminal("x".into()))
+    );
+    assert_eq!(c.precedence["a"], (1, Assoc::Left));
+    c.precedence.clear();
+    let conflicts = c.get_slr_table().unwrap_err();
+    assert!(conflicts
+        .iter()
+        .all(|c| c.kind == LrConflictKind::ShiftReduce));
+}
@@ -1,6 +1,6 @@
-mod cfg;
+use ll1_parser::context_free_grammar;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Token {
     Add,
     Mul,
@@ -20,12 +20,18 @@ fn main() {
             r = RightBracket,
             d = Num(0)
         }
+        value: { i32 }
         rules: {
-            E => T A;
-            A => a T A | ;
-            T => F B;
-            B => m F B | ;
-            F => l E r | d;
+            E => T A { |v| v[0].value() + v[1].value() };
+            A => a T A { |v| v[1].value() + v[2].value() } | { |_| 0 };
+            T => F B { |v| v[0].value() * v[1].value() };
+            B => m F B { |v| v[1].value() * v[2].value() } | { |_| 1 };
+            F => l E r { |v| *v[1].value() } | d {
+                |v| match v[0].token() {
+                    Num(n) => *n,
+                    _ => unreachable!()
+                }
+            };
         }
         start: E
     );
@@ -40,7 +46,9 @@ fn main() {
     }
     println!();
 
-    c.left_recursion_eliminate_unstable();
+    if let Err(e) = c.left_recursion_eliminate() {
+        println!("{}", e);
+    }
     println!("=====GRAMMAR====");
     for r in c.rules.iter() {
         println!("{}", r);
@@ -54,8 +62,70 @@ fn main() {
     println!("{}", c.get_follows());
 
     println!("=====TABLE======");
-    println!("{}", c.get_table());
+    match c.get_table() {
+        Ok(table) => println!("{}", table),
+        Err(conflicts) => {
+            for conflict in conflicts {
+                println!("{}", conflict);
+            }
+        }
+    }
+
+    println!("=====TRACE======");
+    println!("{}", c.parse_trace(&[LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)]).1);
 
     println!("=====PARSE======");
     println!("{:#?}", c.parse(&[LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)]));
+
+    println!("=====CST========");
+    match c.parse_cst(&[LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)]) {
+        Ok(cst) => println!("{}", cst),
+        Err(e) => println!("{}", e),
+    }
+
+    let lr = context_free_grammar!(
+        terminals: {
+            a = Add,
+            m = Mul,
+            l = LeftBracket,
+            r = RightBracket,
+            d = Num(0)
+        }
+        precedence: {
+            left a;
+            left m;
+        }
+        rules: {
+            E => E a E | E m E | l E r | d;
+        }
+        start: E
+    );
+
+    println!("=====LR(0)======");
+    println!("{}", lr.get_lr0_automaton());
+
+    println!("=====LALR(1)====");
+    match lr.get_lalr_table() {
+        Ok(table) => {
+            println!("{}", table);
+            println!("{:#?}", lr.parse_lr(&table, &[LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)]));
+        }
+        Err(conflicts) => {
+            for conflict in conflicts {
+                println!("{}", conflict);
+            }
+        }
+    }
+
+    println!("=====EVAL=======");
+    match c.evaluate(&[LeftBracket, Num(1), Add, Num(2), RightBracket, Mul, Num(3)]) {
+        Ok(v) => println!("(1+2)*3 = {}", v),
+        Err(e) => println!("{}", e),
+    }
+
+    println!("=====EARLEY=====");
+    match lr.parse_earley(&[Num(1), Add, Num(2), Mul, Num(3)], 10) {
+        Ok(trees) => println!("{} parses\n{:#?}", trees.len(), trees),
+        Err(e) => println!("{}", e),
+    }
 }
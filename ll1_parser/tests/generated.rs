@@ -0,0 +1,107 @@
+//! Compiles and runs the parsers under `tests/generated`, which
+//! `codegen::test_generated_sources` keeps equal to the generator output.
+
+// The generated modules are meant to be used whole, a test touches only
+// part of each.
+#[allow(dead_code)]
+mod arith_descent {
+    include!("generated/arith_descent.rs");
+}
+
+#[allow(dead_code)]
+mod arith_table {
+    include!("generated/arith_table.rs");
+}
+
+#[allow(dead_code)]
+mod reserved_descent {
+    include!("generated/reserved_descent.rs");
+}
+
+mod arith {
+    use super::arith_descent::{A, B, E, F, T};
+
+    // `A` and `B` hold the sum and product of what follows them, `values`
+    // being the numbers at the indices of the `d` tokens.
+    pub fn e(tree: &E, values: &[i32]) -> i32 {
+        let E::P0(t1, a1) = tree;
+        t(t1, values) + a(a1, values)
+    }
+
+    fn a(tree: &A, values: &[i32]) -> i32 {
+        match tree {
+            A::P0(_, t1, a1) => t(t1, values) + a(a1, values),
+            A::P1 => 0,
+        }
+    }
+
+    fn t(tree: &T, values: &[i32]) -> i32 {
+        let T::P0(f1, b1) = tree;
+        f(f1, values) * b(b1, values)
+    }
+
+    fn b(tree: &B, values: &[i32]) -> i32 {
+        match tree {
+            B::P0(_, f1, b1) => f(f1, values) * b(b1, values),
+            B::P1 => 1,
+        }
+    }
+
+    fn f(tree: &F, values: &[i32]) -> i32 {
+        match tree {
+            F::P0(_, e1, _) => e(e1, values),
+            F::P1(i) => values[*i],
+        }
+    }
+}
+
+#[test]
+fn test_recursive_descent() {
+    use arith_descent::{parse, ParseError, Terminal::*};
+
+    // (1 + 2) * 3
+    let tokens = [L, D, A, D, R, M, D];
+    let values = [0, 1, 0, 2, 0, 0, 3];
+    let tree = parse(&tokens).unwrap();
+    assert_eq!(arith::e(&tree, &values), 9);
+
+    assert_eq!(
+        parse(&[D, A, M]).unwrap_err(),
+        ParseError {
+            index: 2,
+            found: Some(M),
+            expected: &["l", "d"],
+        }
+    );
+    assert_eq!(parse(&[L, D]).unwrap_err().expected, &["r"]);
+    assert_eq!(parse(&[D, D]).unwrap_err().index, 1);
+}
+
+#[test]
+fn test_table_parser() {
+    use arith_table::{parse, Terminal::*, RULES, VARIABLES};
+
+    let derivation = parse(&[L, D, A, D, R, M, D]).unwrap();
+    assert_eq!(
+        derivation,
+        vec![0, 3, 6, 0, 3, 7, 5, 1, 3, 7, 5, 2, 4, 7, 5, 2]
+    );
+    assert_eq!(VARIABLES[RULES[derivation[0] as usize].left as usize], "E");
+
+    let error = parse(&[D, A, M]).unwrap_err();
+    assert_eq!((error.index, error.found), (2, Some(M)));
+    assert_eq!(error.expected, &["l", "d"]);
+    assert_eq!(parse(&[D, R]).unwrap_err().expected, &["$"]);
+}
+
+#[test]
+fn test_reserved_names() {
+    use reserved_descent::{parse, Terminal, Var0, Var2};
+
+    // Variables named `Parser`, `Terminal` and `ParseError` fall back to
+    // `Var0`, `Var1` and `Var2`.
+    let tree = parse(&[Terminal::Id, Terminal::T1, Terminal::Id]).unwrap();
+    let Var0::P0(_, rest) = tree;
+    assert!(matches!(*rest, Var2::P0(1, 2, _)));
+    assert_eq!(parse(&[Terminal::T1]).unwrap_err().expected, &["id"]);
+}
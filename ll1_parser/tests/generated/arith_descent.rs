@@ -0,0 +1,178 @@
+// Recursive-descent parser generated by ll1_parser, do not edit.
+// Grammar start: E
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminal {
+    /// `a`
+    A,
+    /// `m`
+    M,
+    /// `l`
+    L,
+    /// `r`
+    R,
+    /// `d`
+    D,
+}
+
+pub const TERMINALS: [&str; 5] = ["a", "m", "l", "r", "d"];
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub index: usize,
+    pub found: Option<Terminal>,
+    pub expected: &'static [&'static str],
+}
+
+#[derive(Debug)]
+pub enum E {
+    /// `E => T A`
+    P0(Box<T>, Box<A>),
+}
+
+#[derive(Debug)]
+pub enum A {
+    /// `A => a T A`
+    P0(usize, Box<T>, Box<A>),
+    /// `A => #`
+    P1,
+}
+
+#[derive(Debug)]
+pub enum T {
+    /// `T => F B`
+    P0(Box<F>, Box<B>),
+}
+
+#[derive(Debug)]
+pub enum B {
+    /// `B => m F B`
+    P0(usize, Box<F>, Box<B>),
+    /// `B => #`
+    P1,
+}
+
+#[derive(Debug)]
+pub enum F {
+    /// `F => l E r`
+    P0(usize, Box<E>, usize),
+    /// `F => d`
+    P1(usize),
+}
+
+pub struct Parser<'t> {
+    tokens: &'t [Terminal],
+    pos: usize,
+}
+
+impl<'t> Parser<'t> {
+    pub fn new(tokens: &'t [Terminal]) -> Parser<'t> {
+        Parser { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<Terminal> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn error(&self, expected: &'static [&'static str]) -> ParseError {
+        ParseError {
+            index: self.pos,
+            found: self.peek(),
+            expected,
+        }
+    }
+
+    fn expect(&mut self, t: Terminal, name: &'static [&'static str]) -> Result<usize, ParseError> {
+        if self.peek() == Some(t) {
+            self.pos += 1;
+            Ok(self.pos - 1)
+        } else {
+            Err(self.error(name))
+        }
+    }
+
+    pub fn parse_e(&mut self) -> Result<E, ParseError> {
+        match self.peek() {
+            // E => T A
+            Some(Terminal::L) | Some(Terminal::D) => {
+                let x0 = Box::new(self.parse_t()?);
+                let x1 = Box::new(self.parse_a()?);
+                Ok(E::P0(x0, x1))
+            }
+            _ => Err(self.error(&["l", "d"])),
+        }
+    }
+
+    pub fn parse_a(&mut self) -> Result<A, ParseError> {
+        match self.peek() {
+            // A => a T A
+            Some(Terminal::A) => {
+                let x0 = self.expect(Terminal::A, &["a"])?;
+                let x1 = Box::new(self.parse_t()?);
+                let x2 = Box::new(self.parse_a()?);
+                Ok(A::P0(x0, x1, x2))
+            }
+            // A => #
+            Some(Terminal::R) | None => {
+                Ok(A::P1)
+            }
+            _ => Err(self.error(&["a", "r", "$"])),
+        }
+    }
+
+    pub fn parse_t(&mut self) -> Result<T, ParseError> {
+        match self.peek() {
+            // T => F B
+            Some(Terminal::L) | Some(Terminal::D) => {
+                let x0 = Box::new(self.parse_f()?);
+                let x1 = Box::new(self.parse_b()?);
+                Ok(T::P0(x0, x1))
+            }
+            _ => Err(self.error(&["l", "d"])),
+        }
+    }
+
+    pub fn parse_b(&mut self) -> Result<B, ParseError> {
+        match self.peek() {
+            // B => m F B
+            Some(Terminal::M) => {
+                let x0 = self.expect(Terminal::M, &["m"])?;
+                let x1 = Box::new(self.parse_f()?);
+                let x2 = Box::new(self.parse_b()?);
+                Ok(B::P0(x0, x1, x2))
+            }
+            // B => #
+            Some(Terminal::A) | Some(Terminal::R) | None => {
+                Ok(B::P1)
+            }
+            _ => Err(self.error(&["a", "m", "r", "$"])),
+        }
+    }
+
+    pub fn parse_f(&mut self) -> Result<F, ParseError> {
+        match self.peek() {
+            // F => l E r
+            Some(Terminal::L) => {
+                let x0 = self.expect(Terminal::L, &["l"])?;
+                let x1 = Box::new(self.parse_e()?);
+                let x2 = self.expect(Terminal::R, &["r"])?;
+                Ok(F::P0(x0, x1, x2))
+            }
+            // F => d
+            Some(Terminal::D) => {
+                let x0 = self.expect(Terminal::D, &["d"])?;
+                Ok(F::P1(x0))
+            }
+            _ => Err(self.error(&["l", "d"])),
+        }
+    }
+}
+
+pub fn parse(tokens: &[Terminal]) -> Result<E, ParseError> {
+    let mut parser = Parser::new(tokens);
+    let tree = parser.parse_e()?;
+    match parser.peek() {
+        None => Ok(tree),
+        Some(_) => Err(parser.error(&["$"])),
+    }
+}
@@ -0,0 +1,115 @@
+// Table-driven parser generated by ll1_parser, do not edit.
+// Grammar start: E
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminal {
+    /// `a`
+    A,
+    /// `m`
+    M,
+    /// `l`
+    L,
+    /// `r`
+    R,
+    /// `d`
+    D,
+}
+
+pub const TERMINALS: [&str; 5] = ["a", "m", "l", "r", "d"];
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub index: usize,
+    pub found: Option<Terminal>,
+    pub expected: &'static [&'static str],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sym {
+    T(Terminal),
+    V(u16),
+}
+
+#[derive(Debug)]
+pub struct Rule {
+    pub left: u16,
+    pub right: &'static [Sym],
+}
+
+pub const VARIABLES: [&str; 5] = ["E", "A", "T", "B", "F"];
+
+pub const START: u16 = 0;
+const END: usize = 5;
+
+pub static RULES: [Rule; 8] = [
+    // E => T A
+    Rule { left: 0, right: &[Sym::V(2), Sym::V(1)] },
+    // A => a T A
+    Rule { left: 1, right: &[Sym::T(Terminal::A), Sym::V(2), Sym::V(1)] },
+    // A => #
+    Rule { left: 1, right: &[] },
+    // T => F B
+    Rule { left: 2, right: &[Sym::V(4), Sym::V(3)] },
+    // B => m F B
+    Rule { left: 3, right: &[Sym::T(Terminal::M), Sym::V(4), Sym::V(3)] },
+    // B => #
+    Rule { left: 3, right: &[] },
+    // F => l E r
+    Rule { left: 4, right: &[Sym::T(Terminal::L), Sym::V(0), Sym::T(Terminal::R)] },
+    // F => d
+    Rule { left: 4, right: &[Sym::T(Terminal::D)] },
+];
+
+pub static TABLE: [[Option<u16>; 6]; 5] = [
+    // E
+    [None, None, Some(0), None, Some(0), None],
+    // A
+    [Some(1), None, None, Some(2), None, Some(2)],
+    // T
+    [None, None, Some(3), None, Some(3), None],
+    // B
+    [Some(5), Some(4), None, Some(5), None, Some(5)],
+    // F
+    [None, None, Some(6), None, Some(7), None],
+];
+
+static EXPECTED: [&[&str]; 5] = [
+    &["l", "d"],
+    &["a", "r", "$"],
+    &["l", "d"],
+    &["a", "m", "r", "$"],
+    &["l", "d"],
+];
+
+pub fn parse(tokens: &[Terminal]) -> Result<Vec<u16>, ParseError> {
+    let mut stack = vec![Sym::V(START)];
+    let mut derivation = Vec::new();
+    let mut pos = 0;
+    let error = |pos: usize, expected| ParseError {
+        index: pos,
+        found: tokens.get(pos).copied(),
+        expected,
+    };
+    while let Some(top) = stack.pop() {
+        let a = tokens.get(pos).map_or(END, |t| *t as usize);
+        match top {
+            Sym::T(t) => {
+                if t as usize != a {
+                    return Err(error(pos, std::slice::from_ref(&TERMINALS[t as usize])));
+                }
+                pos += 1;
+            }
+            Sym::V(v) => match TABLE[v as usize][a] {
+                Some(p) => {
+                    derivation.push(p);
+                    stack.extend(RULES[p as usize].right.iter().rev());
+                }
+                None => return Err(error(pos, EXPECTED[v as usize])),
+            },
+        }
+    }
+    if pos < tokens.len() {
+        return Err(error(pos, &["$"]));
+    }
+    Ok(derivation)
+}
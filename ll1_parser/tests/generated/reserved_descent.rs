@@ -0,0 +1,120 @@
+// Recursive-descent parser generated by ll1_parser, do not edit.
+// Grammar start: Parser
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminal {
+    /// `id`
+    Id,
+    /// `,`
+    T1,
+}
+
+pub const TERMINALS: [&str; 2] = ["id", ","];
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub index: usize,
+    pub found: Option<Terminal>,
+    pub expected: &'static [&'static str],
+}
+
+#[derive(Debug)]
+pub enum Var0 {
+    /// `Parser => Terminal ParseError`
+    P0(Box<Var1>, Box<Var2>),
+}
+
+#[derive(Debug)]
+pub enum Var1 {
+    /// `Terminal => id`
+    P0(usize),
+}
+
+#[derive(Debug)]
+pub enum Var2 {
+    /// `ParseError => , id ParseError`
+    P0(usize, usize, Box<Var2>),
+    /// `ParseError => #`
+    P1,
+}
+
+pub struct Parser<'t> {
+    tokens: &'t [Terminal],
+    pos: usize,
+}
+
+impl<'t> Parser<'t> {
+    pub fn new(tokens: &'t [Terminal]) -> Parser<'t> {
+        Parser { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<Terminal> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn error(&self, expected: &'static [&'static str]) -> ParseError {
+        ParseError {
+            index: self.pos,
+            found: self.peek(),
+            expected,
+        }
+    }
+
+    fn expect(&mut self, t: Terminal, name: &'static [&'static str]) -> Result<usize, ParseError> {
+        if self.peek() == Some(t) {
+            self.pos += 1;
+            Ok(self.pos - 1)
+        } else {
+            Err(self.error(name))
+        }
+    }
+
+    pub fn parse_parser(&mut self) -> Result<Var0, ParseError> {
+        match self.peek() {
+            // Parser => Terminal ParseError
+            Some(Terminal::Id) => {
+                let x0 = Box::new(self.parse_terminal()?);
+                let x1 = Box::new(self.parse_parse_error()?);
+                Ok(Var0::P0(x0, x1))
+            }
+            _ => Err(self.error(&["id"])),
+        }
+    }
+
+    pub fn parse_terminal(&mut self) -> Result<Var1, ParseError> {
+        match self.peek() {
+            // Terminal => id
+            Some(Terminal::Id) => {
+                let x0 = self.expect(Terminal::Id, &["id"])?;
+                Ok(Var1::P0(x0))
+            }
+            _ => Err(self.error(&["id"])),
+        }
+    }
+
+    pub fn parse_parse_error(&mut self) -> Result<Var2, ParseError> {
+        match self.peek() {
+            // ParseError => , id ParseError
+            Some(Terminal::T1) => {
+                let x0 = self.expect(Terminal::T1, &[","])?;
+                let x1 = self.expect(Terminal::Id, &["id"])?;
+                let x2 = Box::new(self.parse_parse_error()?);
+                Ok(Var2::P0(x0, x1, x2))
+            }
+            // ParseError => #
+            None => {
+                Ok(Var2::P1)
+            }
+            _ => Err(self.error(&[",", "$"])),
+        }
+    }
+}
+
+pub fn parse(tokens: &[Terminal]) -> Result<Var0, ParseError> {
+    let mut parser = Parser::new(tokens);
+    let tree = parser.parse_parser()?;
+    match parser.peek() {
+        None => Ok(tree),
+        Some(_) => Err(parser.error(&["$"])),
+    }
+}
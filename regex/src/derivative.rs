@@ -0,0 +1,276 @@
+use crate::dfa::DFA;
+use crate::regex2post::RegexToken;
+use std::collections::HashMap;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Regex {
+    Empty,
+    Epsilon,
+    Char(char),
+    Cat(Box<Regex>, Box<Regex>),
+    Alter(Vec<Regex>),
+    And(Vec<Regex>),
+    Closure(Box<Regex>),
+    /// Complement. The derived DFA only has transitions on characters that
+    /// appear in the regex, so `~r` matches the strings over those characters
+    /// that `r` does not match; any other character is rejected.
+    Not(Box<Regex>),
+}
+
+// The constructors below keep every regex in a normal form (similarity rules
+// from Brzozowski's paper), so that the set of derivatives stays finite.
+impl Regex {
+    fn any() -> Regex {
+        Regex::Not(Box::new(Regex::Empty))
+    }
+
+    pub fn cat(r: Regex, s: Regex) -> Regex {
+        match (r, s) {
+            (Regex::Empty, _) | (_, Regex::Empty) => Regex::Empty,
+            (Regex::Epsilon, s) => s,
+            (r, Regex::Epsilon) => r,
+            (Regex::Cat(r1, r2), s) => Regex::cat(*r1, Regex::cat(*r2, s)),
+            (r, s) => Regex::Cat(Box::new(r), Box::new(s)),
+        }
+    }
+
+    pub fn alter(r: Regex, s: Regex) -> Regex {
+        let mut items = Vec::new();
+        for x in [r, s] {
+            match x {
+                Regex::Alter(xs) => items.extend(xs),
+                Regex::Empty => {}
+                x => items.push(x),
+            }
+        }
+        if items.contains(&Regex::any()) {
+            return Regex::any();
+        }
+        items.sort();
+        items.dedup();
+        match items.len() {
+            0 => Regex::Empty,
+            1 => items.pop().unwrap(),
+            _ => Regex::Alter(items),
+        }
+    }
+
+    pub fn and(r: Regex, s: Regex) -> Regex {
+        let mut items = Vec::new();
+        for x in [r, s] {
+            match x {
+                Regex::And(xs) => items.extend(xs),
+                x => items.push(x),
+            }
+        }
+        if items.contains(&Regex::Empty) {
+            return Regex::Empty;
+        }
+        items.retain(|x| *x != Regex::any());
+        items.sort();
+        items.dedup();
+        match items.len() {
+            0 => Regex::any(),
+            1 => items.pop().unwrap(),
+            _ => Regex::And(items),
+        }
+    }
+
+    pub fn closure(r: Regex) -> Regex {
+        match r {
+            Regex::Empty | Regex::Epsilon => Regex::Epsilon,
+            Regex::Closure(_) => r,
+            r => Regex::Closure(Box::new(r)),
+        }
+    }
+
+    pub fn not(r: Regex) -> Regex {
+        match r {
+            Regex::Not(r) => *r,
+            r => Regex::Not(Box::new(r)),
+        }
+    }
+
+    pub fn from_post(p: &[RegexToken]) -> Regex {
+        let mut stack = Vec::new();
+        for t in p {
+            let r = match t {
+                RegexToken::Char(c) => Regex::Char(*c),
+                RegexToken::Epsilon => Regex::Epsilon,
+                RegexToken::Alter => {
+                    let s = stack.pop().unwrap();
+                    Regex::alter(stack.pop().unwrap(), s)
+                }
+                RegexToken::And => {
+                    let s = stack.pop().unwrap();
+                    Regex::and(stack.pop().unwrap(), s)
+                }
+                RegexToken::Cat => {
+                    let s = stack.pop().unwrap();
+                    Regex::cat(stack.pop().unwrap(), s)
+                }
+                RegexToken::Closure => Regex::closure(stack.pop().unwrap()),
+                RegexToken::Not => Regex::not(stack.pop().unwrap()),
+                RegexToken::Bracket => unreachable!(),
+            };
+            stack.push(r);
+        }
+        stack.pop().unwrap_or(Regex::Epsilon)
+    }
+
+    pub fn nullable(&self) -> bool {
+        match self {
+            Regex::Empty | Regex::Char(_) => false,
+            Regex::Epsilon | Regex::Closure(_) => true,
+            Regex::Cat(r, s) => r.nullable() && s.nullable(),
+            Regex::Alter(rs) => rs.iter().any(|r| r.nullable()),
+            Regex::And(rs) => rs.iter().all(|r| r.nullable()),
+            Regex::Not(r) => !r.nullable(),
+        }
+    }
+
+    pub fn derive(&self, c: char) -> Regex {
+        match self {
+            Regex::Empty | Regex::Epsilon => Regex::Empty,
+            Regex::Char(d) => {
+                if *d == c {
+                    Regex::Epsilon
+                } else {
+                    Regex::Empty
+                }
+            }
+            Regex::Cat(r, s) => {
+                let d = Regex::cat(r.derive(c), (**s).clone());
+                if r.nullable() {
+                    Regex::alter(d, s.derive(c))
+                } else {
+                    d
+                }
+            }
+            Regex::Alter(rs) => rs
+                .iter()
+                .fold(Regex::Empty, |acc, r| Regex::alter(acc, r.derive(c))),
+            Regex::And(rs) => rs
+                .iter()
+                .fold(Regex::any(), |acc, r| Regex::and(acc, r.derive(c))),
+            Regex::Closure(r) => Regex::cat(r.derive(c), self.clone()),
+            Regex::Not(r) => Regex::not(r.derive(c)),
+        }
+    }
+
+    pub fn get_accepts(&self) -> Vec<char> {
+        fn collect(r: &Regex, out: &mut Vec<char>) {
+            match r {
+                Regex::Empty | Regex::Epsilon => {}
+                Regex::Char(c) => out.push(*c),
+                Regex::Cat(r, s) => {
+                    collect(r, out);
+                    collect(s, out);
+                }
+                Regex::Alter(rs) | Regex::And(rs) => rs.iter().for_each(|r| collect(r, out)),
+                Regex::Closure(r) | Regex::Not(r) => collect(r, out),
+            }
+        }
+        let mut accepts = Vec::new();
+        collect(self, &mut accepts);
+        accepts.sort_unstable();
+        accepts.dedup();
+        accepts
+    }
+}
+
+/// Builds a DFA whose states are the derivatives of the regex. Complement is
+/// taken relative to the characters appearing in the regex.
+pub fn post2dfa(p: &[RegexToken]) -> DFA {
+    let start = Regex::from_post(p);
+    let accepts = start.get_accepts();
+    let mut states = vec![start.clone()];
+    let mut rename: HashMap<Regex, usize> = HashMap::new();
+    rename.insert(start, 0);
+    let mut table = Vec::new();
+    while table.len() < states.len() {
+        let state = states[table.len()].clone();
+        let state_trans = accepts
+            .iter()
+            .map(|a| {
+                let d = state.derive(*a);
+                if d == Regex::Empty {
+                    return None;
+                }
+                Some(*rename.entry(d.clone()).or_insert_with(|| {
+                    states.push(d);
+                    states.len() - 1
+                }))
+            })
+            .collect::<Vec<_>>();
+        table.push(state_trans);
+    }
+    DFA {
+        accepts,
+        table,
+        start: 0,
+        out: states
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.nullable())
+            .map(|(i, _)| i)
+            .collect(),
+    }
+}
+
+#[test]
+fn test_derivative_cross_check() {
+    let rs = [
+        "abcd",
+        "(abc)|(acc)|(acd)|(abd)",
+        "0*",
+        "ε|((0|1)*0)",
+        "(0|1)*1(0|1)(0|1)",
+        "a(b|c)*d",
+        "(a|b*)c",
+    ];
+    for r in rs {
+        let d = DFA::from_regex_derivative(r);
+        println!("{}", d);
+        assert!(d.equivalent(&DFA::from_regex(r).unwrap()));
+        assert!(d.equivalent(&DFA::from_regex(r).unwrap().minimize()));
+    }
+    assert!(!DFA::from_regex_derivative("a*").equivalent(&DFA::from_regex("a*b").unwrap()));
+}
+
+#[test]
+fn test_derivative_and_not() {
+    let d = DFA::from_regex_derivative("(a|b)*&~((a|b)*aa(a|b)*)");
+    println!("{}", d);
+    assert!(d.is_accept(""));
+    assert!(d.is_accept("ababba"));
+    assert!(!d.is_accept("abaab"));
+    assert!(!d.is_accept("aa"));
+
+    let d = DFA::from_regex_derivative("~(ab)");
+    assert!(d.is_accept(""));
+    assert!(d.is_accept("a"));
+    assert!(d.is_accept("abb"));
+    assert!(!d.is_accept("ab"));
+
+    let d = DFA::from_regex_derivative("(a|b)*&b(a|b)*&(a|b)*a");
+    assert!(d.is_accept("ba"));
+    assert!(d.is_accept("bbaba"));
+    assert!(!d.is_accept("ab"));
+    assert!(!d.is_accept("b"));
+}
+
+// The complement is taken over the characters of the regex, not over every
+// character: `c` is outside the alphabet of `~(ab)`.
+#[test]
+fn test_derivative_not_alphabet() {
+    let d = DFA::from_regex_derivative("~(ab)");
+    assert!(d.is_accept("ba"));
+    assert!(!d.is_accept("c"));
+    assert!(!d.is_accept("ac"));
+
+    let d = DFA::from_regex_derivative("~(ab)&(a|b|c)*");
+    assert!(d.is_accept("c"));
+    assert!(d.is_accept("ac"));
+    assert!(!d.is_accept("ab"));
+}
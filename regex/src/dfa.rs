@@ -1,8 +1,12 @@
+use crate::derivative;
+use crate::followpos;
 use crate::nfa::NFA;
-use crate::nfa2dfa::determinize;
-use std::collections::HashMap;
+use crate::nfa2dfa::{determinize, format_set, try_determinize, DeterminizeError, Limits};
+use crate::regex2post::{regex2post, UnsupportedOperator};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter, Result};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub struct DFA {
     pub accepts: Vec<char>,
@@ -11,6 +15,15 @@ pub struct DFA {
     pub out: Vec<usize>,
 }
 
+#[derive(Debug, Clone)]
+pub struct MinimizeRound {
+    pub round: usize,
+    pub partition: Vec<Vec<usize>>,
+}
+
+#[derive(Debug)]
+pub struct MinimizeTrace(pub Vec<MinimizeRound>);
+
 pub enum TransRes {
     Next(usize),
     Accept,
@@ -18,36 +31,6 @@ pub enum TransRes {
 }
 
 impl DFA {
-    pub fn distinguishable(&self, p: usize, q: usize) -> bool {
-        use TransRes::{Accept, Next, Unaccept};
-        let p_out = self.out.contains(&p);
-        let q_out = self.out.contains(&q);
-        if p_out ^ q_out {
-            return true;
-        }
-        if p == q {
-            return false;
-        }
-        let mut accepts: Vec<_> = self.accepts.iter().map(|a| Some(*a)).collect();
-        accepts.push(None);
-        for a in accepts {
-            let r = self.get_trans(p, a);
-            let s = self.get_trans(q, a);
-            match (r, s) {
-                (Accept, Accept) | (Unaccept, Unaccept) => {}
-                (Accept, _) | (_, Accept) | (Unaccept, _) | (_, Unaccept) => {
-                    return true;
-                }
-                (Next(rc), Next(sc)) => {
-                    if self.distinguishable(rc, sc) {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
-    }
-
     pub fn get_trans(&self, s: usize, c: Option<char>) -> TransRes {
         if let Some(ch) = c {
             let oi = self.accepts.iter().enumerate().find(|(_, x)| **x == ch);
@@ -67,53 +50,67 @@ impl DFA {
         }
     }
 
-    pub fn get_nondistinguishable_states(&self) -> Vec<Vec<usize>> {
-        let node_cnt = self.table.len();
-        let mut states = Vec::new();
-        for i in 0..node_cnt {
-            for j in (i + 1)..node_cnt {
-                if self.distinguishable(i, j) {
-                    let has_i = states.iter().flatten().any(|x: &usize| *x == i);
-                    let has_j = states.iter().flatten().any(|x: &usize| *x == j);
-                    if !has_i {
-                        states.push(vec![i]);
-                    }
-                    if !has_j {
-                        states.push(vec![j]);
-                    }
-                } else {
-                    let idx = states
+    /// Splits the states into accepting and non-accepting, then refines the
+    /// blocks by the blocks their transitions lead to until nothing splits.
+    /// `on_round` sees the partition after every round.
+    pub fn get_partition_with<F: FnMut(&MinimizeRound)>(&self, mut on_round: F) -> Vec<Vec<usize>> {
+        let (out, other): (Vec<usize>, Vec<usize>) =
+            (0..self.table.len()).partition(|s| self.out.contains(s));
+        let mut partition: Vec<_> = vec![other, out]
+            .into_iter()
+            .filter(|b| !b.is_empty())
+            .collect();
+        let mut round = 0;
+        loop {
+            on_round(&MinimizeRound {
+                round,
+                partition: partition.clone(),
+            });
+            let mut block_of = vec![0; self.table.len()];
+            for (i, block) in partition.iter().enumerate() {
+                for s in block {
+                    block_of[*s] = i;
+                }
+            }
+            let mut refined = Vec::new();
+            for block in partition.iter() {
+                let mut groups: Vec<(Vec<Option<usize>>, Vec<usize>)> = Vec::new();
+                for s in block {
+                    let sig: Vec<_> = self.table[*s]
                         .iter()
-                        .enumerate()
-                        .filter(|(_, s)| s.contains(&i) || s.contains(&j))
-                        .map(|(x, _)| x)
-                        .next();
-                    if let Some(s_idx) = idx {
-                        states[s_idx].push(i);
-                        states[s_idx].push(j);
-                    } else {
-                        states.push(vec![i, j]);
+                        .map(|n| n.map(|n| block_of[n]))
+                        .collect();
+                    match groups.iter_mut().find(|g| g.0 == sig) {
+                        Some(g) => g.1.push(*s),
+                        None => groups.push((sig, vec![*s])),
                     }
                 }
+                refined.extend(groups.into_iter().map(|g| g.1));
+            }
+            if refined.len() == partition.len() {
+                return partition;
             }
+            partition = refined;
+            round += 1;
         }
-        states
-            .into_iter()
-            .map(|mut s| {
-                s.sort_unstable();
-                s.dedup();
-                s
-            })
-            .collect()
     }
 
     pub fn minimize(&self) -> DFA {
-        let states = self.get_nondistinguishable_states();
+        self.minimize_with(|_| {})
+    }
+
+    pub fn minimize_trace(&self) -> (DFA, MinimizeTrace) {
+        let mut rounds = Vec::new();
+        let dfa = self.minimize_with(|r| rounds.push(r.clone()));
+        (dfa, MinimizeTrace(rounds))
+    }
+
+    pub fn minimize_with<F: FnMut(&MinimizeRound)>(&self, on_round: F) -> DFA {
+        let states = self.get_partition_with(on_round);
         let rename: HashMap<usize, usize> = states
             .iter()
             .enumerate()
-            .map(|(i, x)| x.iter().map(move |tx| (*tx, i)))
-            .flatten()
+            .flat_map(|(i, x)| x.iter().map(move |tx| (*tx, i)))
             .collect();
         let mut table = Vec::new();
         for s in states {
@@ -152,8 +149,54 @@ impl DFA {
         self.out.contains(&state)
     }
 
-    pub fn from_regex(r: &str) -> DFA {
-        determinize(&NFA::from_regex(r))
+    pub fn from_regex(r: &str) -> std::result::Result<DFA, UnsupportedOperator> {
+        Ok(determinize(&NFA::from_regex(r)?))
+    }
+
+    pub fn try_from_regex(r: &str, limits: &Limits) -> std::result::Result<DFA, DeterminizeError> {
+        try_determinize(&NFA::from_regex(r)?, limits)
+            .map_err(|e| e.with_regex_len(r.chars().count()))
+    }
+
+    /// Builds the DFA with Brzozowski derivatives. This is the only
+    /// construction that supports `&` and `~`. The alphabet is the set of
+    /// characters appearing in `r`, so `~(ab)` rejects `c`.
+    pub fn from_regex_derivative(r: &str) -> DFA {
+        derivative::post2dfa(&regex2post(r))
+    }
+
+    pub fn from_regex_followpos(r: &str) -> std::result::Result<DFA, UnsupportedOperator> {
+        followpos::post2dfa(&regex2post(r))
+    }
+
+    fn next(&self, s: Option<usize>, c: char) -> Option<usize> {
+        match self.get_trans(s?, Some(c)) {
+            TransRes::Next(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn equivalent(&self, other: &DFA) -> bool {
+        let mut accepts = self.accepts.clone();
+        accepts.extend(other.accepts.iter());
+        accepts.sort_unstable();
+        accepts.dedup();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(Some(self.start), Some(other.start))];
+        while let Some((p, q)) = stack.pop() {
+            if !visited.insert((p, q)) {
+                continue;
+            }
+            let p_out = p.is_some_and(|p| self.out.contains(&p));
+            let q_out = q.is_some_and(|q| other.out.contains(&q));
+            if p_out != q_out {
+                return false;
+            }
+            for a in accepts.iter() {
+                stack.push((self.next(p, *a), other.next(q, *a)));
+            }
+        }
+        true
     }
 }
 
@@ -195,3 +238,36 @@ impl Display for DFA {
         Ok(())
     }
 }
+
+impl Display for MinimizeTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Round | Partition\n--- | ---")?;
+        for r in self.0.iter() {
+            write!(
+                f,
+                "\n{} | {}",
+                r.round,
+                r.partition
+                    .iter()
+                    .map(|b| format_set(b))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_minimize_trace() {
+    let d = DFA::from_regex("(a|b)*abb").unwrap();
+    let (md, trace) = d.minimize_trace();
+    println!("{}", d);
+    println!("{}", trace);
+    println!("{}", md);
+    assert_eq!(4, md.table.len());
+    assert_eq!(0, trace.0[0].round);
+    assert_eq!(2, trace.0[0].partition.len());
+    assert_eq!(md.table.len(), trace.0.last().unwrap().partition.len());
+    assert!(md.equivalent(&d));
+}
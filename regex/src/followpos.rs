@@ -0,0 +1,182 @@
+use crate::dfa::DFA;
+use crate::regex2post::{RegexToken, UnsupportedOperator};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct Positions {
+    pub symbols: Vec<char>,
+    pub nullable: bool,
+    pub first: Vec<usize>,
+    pub last: Vec<usize>,
+    pub follow: Vec<Vec<usize>>,
+}
+
+fn union(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut u: Vec<_> = a.iter().chain(b.iter()).copied().collect();
+    u.sort_unstable();
+    u.dedup();
+    u
+}
+
+impl Positions {
+    pub fn from_post(p: &[RegexToken]) -> Result<Positions, UnsupportedOperator> {
+        let mut symbols = Vec::new();
+        let mut follow: Vec<Vec<usize>> = Vec::new();
+        let mut stack: Vec<(bool, Vec<usize>, Vec<usize>)> = Vec::new();
+        for t in p {
+            match t {
+                RegexToken::Char(c) => {
+                    let pos = symbols.len();
+                    symbols.push(*c);
+                    follow.push(Vec::new());
+                    stack.push((false, vec![pos], vec![pos]));
+                }
+                RegexToken::Epsilon => {
+                    stack.push((true, Vec::new(), Vec::new()));
+                }
+                RegexToken::Alter => {
+                    let (n2, f2, l2) = stack.pop().unwrap();
+                    let (n1, f1, l1) = stack.pop().unwrap();
+                    stack.push((n1 || n2, union(&f1, &f2), union(&l1, &l2)));
+                }
+                RegexToken::Cat => {
+                    let (n2, f2, l2) = stack.pop().unwrap();
+                    let (n1, f1, l1) = stack.pop().unwrap();
+                    for i in l1.iter() {
+                        follow[*i] = union(&follow[*i], &f2);
+                    }
+                    stack.push((
+                        n1 && n2,
+                        if n1 { union(&f1, &f2) } else { f1 },
+                        if n2 { union(&l1, &l2) } else { l2 },
+                    ));
+                }
+                RegexToken::Closure => {
+                    let (_, f, l) = stack.pop().unwrap();
+                    for i in l.iter() {
+                        follow[*i] = union(&follow[*i], &f);
+                    }
+                    stack.push((true, f, l));
+                }
+                RegexToken::And | RegexToken::Not => {
+                    return Err(UnsupportedOperator {
+                        operator: if *t == RegexToken::And { '&' } else { '~' },
+                        construction: "position automaton",
+                    })
+                }
+                RegexToken::Bracket => unreachable!(),
+            }
+        }
+        let (nullable, first, last) = stack.pop().unwrap_or((true, Vec::new(), Vec::new()));
+        Ok(Positions {
+            symbols,
+            nullable,
+            first,
+            last,
+            follow,
+        })
+    }
+}
+
+/// Builds a DFA from the followpos sets of the regex augmented with an end
+/// marker, as in the Dragon Book. The end marker takes the position after the
+/// last symbol.
+pub fn post2dfa(p: &[RegexToken]) -> Result<DFA, UnsupportedOperator> {
+    let pos = Positions::from_post(p)?;
+    let end = pos.symbols.len();
+    let mut follow = pos.follow.clone();
+    for i in pos.last.iter() {
+        follow[*i].push(end);
+    }
+    let start = if pos.nullable {
+        union(&pos.first, &[end])
+    } else {
+        pos.first.clone()
+    };
+    let mut accepts = pos.symbols.clone();
+    accepts.sort_unstable();
+    accepts.dedup();
+
+    let mut states = vec![start.clone()];
+    let mut rename: HashMap<Vec<usize>, usize> = HashMap::new();
+    rename.insert(start, 0);
+    let mut table = Vec::new();
+    while table.len() < states.len() {
+        let state = states[table.len()].clone();
+        let state_trans = accepts
+            .iter()
+            .map(|a| {
+                let mut new_state: Vec<_> = state
+                    .iter()
+                    .filter(|i| **i != end && pos.symbols[**i] == *a)
+                    .flat_map(|i| follow[*i].iter().copied())
+                    .collect();
+                new_state.sort_unstable();
+                new_state.dedup();
+                if new_state.is_empty() {
+                    return None;
+                }
+                Some(*rename.entry(new_state.clone()).or_insert_with(|| {
+                    states.push(new_state);
+                    states.len() - 1
+                }))
+            })
+            .collect::<Vec<_>>();
+        table.push(state_trans);
+    }
+    Ok(DFA {
+        accepts,
+        table,
+        start: 0,
+        out: states
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.contains(&end))
+            .map(|(i, _)| i)
+            .collect(),
+    })
+}
+
+#[test]
+fn test_positions() {
+    use crate::regex2post::regex2post;
+    let pos = Positions::from_post(&regex2post("(a|b)*abb")).unwrap();
+    assert_eq!(pos.symbols, vec!['a', 'b', 'a', 'b', 'b']);
+    assert!(!pos.nullable);
+    assert_eq!(pos.first, vec![0, 1, 2]);
+    assert_eq!(pos.last, vec![4]);
+    assert_eq!(
+        pos.follow,
+        vec![vec![0, 1, 2], vec![0, 1, 2], vec![3], vec![4], vec![]]
+    );
+}
+
+#[test]
+fn test_followpos_cross_check() {
+    let rs = [
+        "abcd",
+        "(abc)|(acc)|(acd)|(abd)",
+        "0*",
+        "ε|((0|1)*0)",
+        "(0|1)*1(0|1)(0|1)",
+        "a(b|c)*d",
+        "(a|b*)c",
+    ];
+    for r in rs {
+        let d = DFA::from_regex_followpos(r).unwrap();
+        println!("{}", d);
+        assert!(d.equivalent(&DFA::from_regex(r).unwrap()));
+        assert!(d.equivalent(&DFA::from_regex_derivative(r)));
+    }
+    let d = DFA::from_regex_followpos("(a|b)*abb").unwrap();
+    assert_eq!(4, d.table.len());
+}
+
+#[test]
+fn test_followpos_unsupported() {
+    assert_eq!(
+        DFA::from_regex_followpos("a&b").unwrap_err().to_string(),
+        "`&` is not supported by position automaton construction"
+    );
+    assert!(crate::nfa::NFA::from_regex_glushkov("~a").is_err());
+}
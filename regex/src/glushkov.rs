@@ -0,0 +1,49 @@
+use crate::followpos::Positions;
+use crate::nfa::{NFANode, NFA};
+use crate::regex2post::{RegexToken, UnsupportedOperator};
+
+/// Builds the ε-free position automaton of the regex: one start node plus
+/// one node per symbol occurrence, so `n` symbols give exactly `n + 1` nodes.
+pub fn post2nfa(p: &[RegexToken]) -> Result<NFA, UnsupportedOperator> {
+    let pos = Positions::from_post(p)?;
+    let mut nfa = NFA::new();
+    nfa.start = nfa.add_node(NFANode::new(None));
+    for c in pos.symbols.iter() {
+        nfa.add_node(NFANode::new(Some(*c)));
+    }
+    for f in pos.first.iter() {
+        nfa.add_edge(nfa.start, f + 1);
+    }
+    for (i, follow) in pos.follow.iter().enumerate() {
+        for f in follow {
+            nfa.add_edge(i + 1, f + 1);
+        }
+    }
+    if pos.nullable {
+        nfa.out.push(nfa.start);
+    }
+    nfa.out.extend(pos.last.iter().map(|x| x + 1));
+    Ok(nfa)
+}
+
+#[test]
+fn test_glushkov() {
+    use crate::dfa::DFA;
+    use crate::nfa2dfa::determinize;
+    let rs = [
+        "abcd",
+        "(abc)|(acc)|(acd)|(abd)",
+        "0*",
+        "ε|((0|1)*0)",
+        "(0|1)*1(0|1)(0|1)",
+        "a(b|c)*d",
+    ];
+    for r in rs {
+        let n = NFA::from_regex_glushkov(r).unwrap();
+        println!("{}", n);
+        let symbols = r.chars().filter(|c| !"()|*ε".contains(*c)).count();
+        assert_eq!(symbols + 1, n.nodes.len());
+        assert!(n.is_epsilon_free());
+        assert!(determinize(&n).equivalent(&DFA::from_regex(r).unwrap()));
+    }
+}
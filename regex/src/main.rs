@@ -1,30 +1,74 @@
+#![allow(clippy::bool_assert_comparison)]
+
 mod nfa;
 mod dfa;
 mod regex2post;
 mod post2nfa;
 mod nfa2dfa;
+mod derivative;
+mod followpos;
+mod glushkov;
 
+use dfa::DFA;
 use nfa::NFA;
+use std::time::Instant;
 
 fn main() {
     let r = "ε|((0|1)*0)";
     println!("Regex: {}", r);
-    let n = NFA::from_regex(r);
+    let n = NFA::from_regex(r).unwrap();
     println!("{:?}", n.get_reach(0, Some('0')));
     println!("Regex To NFA: ");
     println!("{}", n);
-    let d = nfa2dfa::determinize(&n);
+    let (d, trace) = nfa2dfa::determinize_trace(&n);
     println!("NFA To DFA: ");
+    println!("{}", trace);
     println!("{}", d);
+    let (md, trace) = d.minimize_trace();
     println!("Minimize DFA: ");
-    println!("{}", d.minimize());
+    println!("{}", trace);
+    println!("{}", md);
+    println!("Regex To NFA (Glushkov): ");
+    println!("{}", NFA::from_regex_glushkov(r).unwrap());
+    println!("Remove ε: ");
+    let e = n.remove_epsilon();
+    println!("{}", e);
+    println!("ε-free: {}", e.is_epsilon_free());
+    println!("Regex To DFA (followpos): ");
+    let f = DFA::from_regex_followpos(r).unwrap();
+    println!("{}", f);
+    println!("Equivalent to Thompson: {}", f.equivalent(&md));
+
+    let r = "(a|b)*&~(a*)";
+    println!("Regex To DFA (derivative): {}", r);
+    let d = DFA::from_regex_derivative(r).minimize();
+    println!("{}", d);
+    println!("Accepts \"ab\": {}", d.is_accept("ab"));
+    if let Err(e) = DFA::from_regex(r) {
+        println!("Thompson: {}", e);
+    }
+
+    let r = "(0|1)*1(0|1)(0|1)(0|1)(0|1)(0|1)";
+    let now = Instant::now();
+    let d = DFA::from_regex(r).unwrap();
+    println!("Thompson:  {} states in {:?}", d.table.len(), now.elapsed());
+    let now = Instant::now();
+    let d = DFA::from_regex_followpos(r).unwrap();
+    println!("Followpos: {} states in {:?}", d.table.len(), now.elapsed());
+    let limits = nfa2dfa::Limits {
+        max_states: Some(32),
+        timeout: None,
+    };
+    if let Err(e) = DFA::try_from_regex(r, &limits) {
+        println!("Limited:   {}", e);
+    }
 }
 
 #[test]
 fn test_regex_cat() {
     use dfa::DFA;
     let r = "abcd";
-    let d = DFA::from_regex(r);
+    let d = DFA::from_regex(r).unwrap();
     println!("{}", d);
     assert_eq!(true, d.is_accept("abcd")); 
     assert_eq!(false, d.is_accept("abc")); 
@@ -39,7 +83,7 @@ fn test_regex_cat() {
 fn test_regex_alter() {
     use dfa::DFA;
     let r = "(abc)|(acc)|(acd)|(abd)";
-    let d = DFA::from_regex(r);
+    let d = DFA::from_regex(r).unwrap();
     println!("{}", d);
     assert_eq!(true, d.is_accept("abc")); 
     assert_eq!(true, d.is_accept("acc")); 
@@ -56,11 +100,22 @@ fn test_regex_alter() {
     assert_eq!(false, md.is_accept("acb")); 
 }
 
+#[test]
+fn test_regex_alter_precedence() {
+    use dfa::DFA;
+    let d = DFA::from_regex("ab|c").unwrap();
+    println!("{}", d);
+    assert_eq!(true, d.is_accept("ab"));
+    assert_eq!(true, d.is_accept("c"));
+    assert_eq!(false, d.is_accept("ac"));
+    assert_eq!(false, d.is_accept("abc"));
+}
+
 #[test]
 fn test_regex_closure() {
     use dfa::DFA;
     let r = "0*";
-    let d = DFA::from_regex(r);
+    let d = DFA::from_regex(r).unwrap();
     println!("{}", d);
     assert_eq!(true, d.is_accept("")); 
     assert_eq!(true, d.is_accept("0000000")); 
@@ -79,7 +134,7 @@ fn test_regex_closure() {
 fn test_regex_integrate() {
     use dfa::DFA;
     let r = "ε|((0|1)*0)";
-    let d = DFA::from_regex(r);
+    let d = DFA::from_regex(r).unwrap();
     println!("{}", d);
     assert_eq!(true, d.is_accept("0010")); 
     assert_eq!(true, d.is_accept("111001111110")); 
@@ -100,7 +155,7 @@ fn test_regex_integrate() {
 fn test_regex_big() {
     use dfa::DFA;
     let r = "(0|1|2|3|4|5|6|7|8|9)(0|1|2|3|4|5|6|7|8|9)(0|1|2|3|4|5|6|7|8|9)(0|1|2|3|4|5|6|7|8|9)-(0|1|2|3|4|5|6|7|8|9)(0|1|2|3|4|5|6|7|8|9)-(0|1|2|3|4|5|6|7|8|9)(0|1|2|3|4|5|6|7|8|9)";
-    let d = DFA::from_regex(r);
+    let d = DFA::from_regex(r).unwrap();
     println!("{}", d);
     assert_eq!(true, d.is_accept("2021-10-12")); 
     assert_eq!(true, d.is_accept("1999-99-99")); 
@@ -1,13 +1,16 @@
+use crate::glushkov;
 use crate::post2nfa::post2nfa;
-use crate::regex2post::regex2post;
+use crate::regex2post::{regex2post, UnsupportedOperator};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub struct NFA {
     pub nodes: Vec<NFANode>,
     pub edges: Vec<(usize, usize)>,
     pub start: usize,
-    pub out: usize,
+    pub out: Vec<usize>,
 }
 
 impl NFA {
@@ -16,14 +19,18 @@ impl NFA {
             nodes: Vec::new(),
             edges: Vec::new(),
             start: 0,
-            out: 0,
+            out: Vec::new(),
         }
     }
 
-    pub fn from_regex(r: &str) -> NFA {
+    pub fn from_regex(r: &str) -> std::result::Result<NFA, UnsupportedOperator> {
         post2nfa(&regex2post(r))
     }
 
+    pub fn from_regex_glushkov(r: &str) -> std::result::Result<NFA, UnsupportedOperator> {
+        glushkov::post2nfa(&regex2post(r))
+    }
+
     pub fn add_node(&mut self, node: NFANode) -> usize {
         let idx = self.nodes.len();
         self.nodes.push(node);
@@ -52,9 +59,8 @@ impl NFA {
 
     pub fn get_reach(&self, s: usize, a: Option<char>) -> Vec<usize> {
         let mut reached = Vec::new();
-        let mut stack = vec![(s, a == None)];
-        while !stack.is_empty() {
-            let (cur, used) = stack.pop().unwrap();
+        let mut stack = vec![(s, a.is_none())];
+        while let Some((cur, used)) = stack.pop() {
             if used {
                 reached.push(cur);
             }
@@ -71,6 +77,51 @@ impl NFA {
         reached.dedup();
         reached
     }
+
+    pub fn is_epsilon_free(&self) -> bool {
+        self.nodes
+            .iter()
+            .enumerate()
+            .all(|(i, n)| i == self.start || n.accept.is_some())
+            && self.edges.iter().all(|e| e.1 != self.start)
+    }
+
+    /// Removes ε-nodes. Every node of the result except the start consumes a
+    /// symbol when entered, so `get_reach` never has to chase ε-transitions.
+    pub fn remove_epsilon(&self) -> NFA {
+        let mut nfa = NFA::new();
+        let mut rename: HashMap<usize, usize> = HashMap::new();
+        nfa.start = nfa.add_node(NFANode::new(None));
+        let mut stack = vec![(self.start, nfa.start)];
+        while let Some((old, new)) = stack.pop() {
+            let closure = self.get_reach(old, None);
+            if closure.iter().any(|s| self.out.contains(s)) {
+                nfa.out.push(new);
+            }
+            let mut targets: Vec<_> = self
+                .edges
+                .iter()
+                .filter(|x| closure.contains(&x.0) && self.nodes[x.1].accept.is_some())
+                .map(|x| x.1)
+                .collect();
+            targets.sort_unstable();
+            targets.dedup();
+            for target in targets {
+                let idx = match rename.get(&target) {
+                    Some(idx) => *idx,
+                    None => {
+                        let idx = nfa.add_node(NFANode::new(self.nodes[target].accept));
+                        rename.insert(target, idx);
+                        stack.push((target, idx));
+                        idx
+                    }
+                };
+                nfa.add_edge(new, idx);
+            }
+        }
+        nfa.out.sort_unstable();
+        nfa
+    }
 }
 
 impl Display for NFA {
@@ -78,7 +129,7 @@ impl Display for NFA {
         let accepts = self.get_accepts();
         let idx_max = self.nodes.len() - 1;
         let start = self.start;
-        let out = self.out;
+        let out = &self.out;
         write!(
             f,
             "NFA  | {}",
@@ -95,11 +146,11 @@ impl Display for NFA {
             write!(
                 f,
                 "\n{}{} | {}",
-                if i == start && i == out {
+                if i == start && out.contains(&i) {
                     "->*"
                 } else if i == start {
                     "-> "
-                } else if i == out {
+                } else if out.contains(&i) {
                     "  *"
                 } else {
                     "   "
@@ -134,3 +185,24 @@ impl NFANode {
         NFANode { accept }
     }
 }
+
+#[test]
+fn test_remove_epsilon() {
+    use crate::dfa::DFA;
+    use crate::nfa2dfa::determinize;
+    let rs = [
+        "abcd",
+        "0*",
+        "ε|((0|1)*0)",
+        "(0|1)*1(0|1)(0|1)",
+        "(a|b*)c",
+        "ε",
+    ];
+    for r in rs {
+        let n = NFA::from_regex(r).unwrap();
+        let e = n.remove_epsilon();
+        println!("{}", e);
+        assert!(e.is_epsilon_free());
+        assert!(determinize(&e).equivalent(&DFA::from_regex(r).unwrap()));
+    }
+}
@@ -1,42 +1,209 @@
 use crate::dfa::DFA;
 use crate::nfa::NFA;
+use crate::regex2post::UnsupportedOperator;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct DeterminizeStep {
+    pub state: Vec<usize>,
+    pub moves: Vec<(char, Vec<usize>, Vec<usize>)>,
+    pub discovered: Vec<Vec<usize>>,
+}
+
+#[derive(Debug)]
+pub struct DeterminizeTrace {
+    pub accepts: Vec<char>,
+    pub start: Vec<usize>,
+    pub steps: Vec<DeterminizeStep>,
+}
+
+/// Bounds for the subset construction, which can create exponentially many
+/// states. `None` means unbounded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub max_states: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+/// `regex_len` is the length in characters of the regex the NFA was built
+/// from, when it is known.
+#[derive(Debug, PartialEq)]
+pub enum DeterminizeError {
+    TooManyStates {
+        limit: usize,
+        nfa_nodes: usize,
+        regex_len: Option<usize>,
+    },
+    Timeout {
+        limit: Duration,
+        states: usize,
+        nfa_nodes: usize,
+        regex_len: Option<usize>,
+    },
+    /// The regex could not be turned into an NFA.
+    Unsupported(UnsupportedOperator),
+}
+
+impl DeterminizeError {
+    pub fn with_regex_len(mut self, len: usize) -> Self {
+        match &mut self {
+            DeterminizeError::TooManyStates { regex_len, .. }
+            | DeterminizeError::Timeout { regex_len, .. } => *regex_len = Some(len),
+            DeterminizeError::Unsupported(_) => {}
+        }
+        self
+    }
+}
+
+fn format_source(nfa_nodes: usize, regex_len: Option<usize>) -> String {
+    match regex_len {
+        Some(len) => format!(
+            "NFA with {} nodes from a regex of {} characters",
+            nfa_nodes, len
+        ),
+        None => format!("NFA with {} nodes", nfa_nodes),
+    }
+}
+
+impl Display for DeterminizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            DeterminizeError::TooManyStates {
+                limit,
+                nfa_nodes,
+                regex_len,
+            } => write!(
+                f,
+                "DFA exceeds {} states ({})",
+                limit,
+                format_source(*nfa_nodes, *regex_len)
+            ),
+            DeterminizeError::Timeout {
+                limit,
+                states,
+                nfa_nodes,
+                regex_len,
+            } => write!(
+                f,
+                "Determinization timed out after {:?} with {} states ({})",
+                limit,
+                states,
+                format_source(*nfa_nodes, *regex_len)
+            ),
+            DeterminizeError::Unsupported(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DeterminizeError {}
+
+impl From<UnsupportedOperator> for DeterminizeError {
+    fn from(e: UnsupportedOperator) -> Self {
+        DeterminizeError::Unsupported(e)
+    }
+}
+
+pub fn format_set(s: &[usize]) -> String {
+    format!(
+        "{{{}}}",
+        s.iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
 
 pub fn determinize(nfa: &NFA) -> DFA {
+    determinize_with(nfa, |_| {})
+}
+
+pub fn try_determinize(nfa: &NFA, limits: &Limits) -> std::result::Result<DFA, DeterminizeError> {
+    try_determinize_with(nfa, limits, |_| {})
+}
+
+pub fn determinize_trace(nfa: &NFA) -> (DFA, DeterminizeTrace) {
+    let mut steps = Vec::new();
+    let dfa = determinize_with(nfa, |s| steps.push(s.clone()));
+    let trace = DeterminizeTrace {
+        accepts: dfa.accepts.clone(),
+        start: nfa.get_reach(nfa.start, None),
+        steps,
+    };
+    (dfa, trace)
+}
+
+/// Subset construction. `on_step` is called once for every popped state set
+/// with its moves on each symbol (before and after ε-closure) and the state
+/// sets discovered from it.
+pub fn determinize_with<F: FnMut(&DeterminizeStep)>(nfa: &NFA, on_step: F) -> DFA {
+    try_determinize_with(nfa, &Limits::default(), on_step).unwrap()
+}
+
+pub fn try_determinize_with<F: FnMut(&DeterminizeStep)>(
+    nfa: &NFA,
+    limits: &Limits,
+    mut on_step: F,
+) -> std::result::Result<DFA, DeterminizeError> {
+    let begin = Instant::now();
     let start = nfa.get_reach(nfa.start, None);
     let mut table = Vec::new();
     let mut states = vec![start.clone()];
-    let accepts: Vec<_> = nfa
-        .get_accepts()
-        .into_iter()
-        .filter(|x| matches!(x, Some(_)))
-        .map(|x| x.unwrap())
-        .collect();
-    while !states.is_empty() {
-        let state = states.pop().unwrap();
-        let new_states: Vec<_> = accepts
+    let accepts: Vec<_> = nfa.get_accepts().into_iter().flatten().collect();
+    while let Some(state) = states.pop() {
+        if let Some(limit) = limits.timeout {
+            if begin.elapsed() > limit {
+                return Err(DeterminizeError::Timeout {
+                    limit,
+                    states: table.len() + states.len() + 1,
+                    nfa_nodes: nfa.nodes.len(),
+                    regex_len: None,
+                });
+            }
+        }
+        let moves: Vec<_> = accepts
             .iter()
             .map(|a| {
-                let mut new_state: Vec<_> = state
+                let mut moved: Vec<_> = state
                     .iter()
-                    .map(|s| nfa.get_reach(*s, Some(*a)))
-                    .flatten()
+                    .flat_map(|s| nfa.get_trans(*s, Some(*a)))
                     .collect();
+                moved.sort_unstable();
+                moved.dedup();
+                let mut new_state: Vec<_> =
+                    moved.iter().flat_map(|s| nfa.get_reach(*s, None)).collect();
                 new_state.sort_unstable();
                 new_state.dedup();
-                new_state
+                (*a, moved, new_state)
             })
             .collect();
-        //println!("{:?} => {:?}", state, new_states);
-        table.push((state, new_states.clone()));
+        let new_states: Vec<_> = moves.iter().map(|m| m.2.clone()).collect();
+        table.push((state.clone(), new_states.clone()));
+        let mut discovered = Vec::new();
         for new_state in new_states {
             if !new_state.is_empty()
                 && !table.iter().any(|s| s.0 == new_state)
                 && !states.contains(&new_state)
             {
+                discovered.push(new_state.clone());
                 states.push(new_state)
             }
         }
+        if let Some(limit) = limits.max_states {
+            if table.len() + states.len() > limit {
+                return Err(DeterminizeError::TooManyStates {
+                    limit,
+                    nfa_nodes: nfa.nodes.len(),
+                    regex_len: None,
+                });
+            }
+        }
+        on_step(&DeterminizeStep {
+            state,
+            moves,
+            discovered,
+        });
     }
 
     let rename: HashMap<Vec<usize>, usize> = table
@@ -44,8 +211,7 @@ pub fn determinize(nfa: &NFA) -> DFA {
         .enumerate()
         .map(|(i, s)| (s.0.clone(), i))
         .collect();
-    //println!("{:?}", rename);
-    DFA {
+    Ok(DFA {
         accepts,
         table: table
             .into_iter()
@@ -58,8 +224,113 @@ pub fn determinize(nfa: &NFA) -> DFA {
         start: *rename.get(&start).unwrap(),
         out: rename
             .into_iter()
-            .filter(|(s, _)| s.contains(&nfa.out))
+            .filter(|(s, _)| s.iter().any(|x| nfa.out.contains(x)))
             .map(|(_, i)| i)
             .collect(),
+    })
+}
+
+impl Display for DeterminizeTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "Step | State | {} | New",
+            self.accepts
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(" | ")
+        )?;
+        write!(f, "\n---{}", " | ---".repeat(self.accepts.len() + 2))?;
+        for (i, step) in self.steps.iter().enumerate() {
+            write!(
+                f,
+                "\n{} | {}{} | {} | {}",
+                i,
+                if step.state == self.start { "-> " } else { "" },
+                format_set(&step.state),
+                step.moves
+                    .iter()
+                    .map(|(_, m, c)| if m.is_empty() {
+                        "/".into()
+                    } else {
+                        format!("{} => {}", format_set(m), format_set(c))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+                if step.discovered.is_empty() {
+                    "/".into()
+                } else {
+                    step.discovered
+                        .iter()
+                        .map(|s| format_set(s))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+            )?;
+        }
+        Ok(())
     }
 }
+
+#[test]
+fn test_determinize_trace() {
+    let n = NFA::from_regex("(a|b)*abb").unwrap();
+    let (d, trace) = determinize_trace(&n);
+    println!("{}", trace);
+    assert_eq!(d.table.len(), trace.steps.len());
+    assert_eq!(trace.start, trace.steps[0].state);
+    assert_eq!(
+        d.table.len(),
+        1 + trace
+            .steps
+            .iter()
+            .map(|s| s.discovered.len())
+            .sum::<usize>()
+    );
+    assert!(trace
+        .to_string()
+        .starts_with("Step | State | a | b | New\n--- | ---"));
+}
+
+#[test]
+fn test_determinize_limits() {
+    let r = "(0|1)*1(0|1)(0|1)(0|1)(0|1)(0|1)(0|1)(0|1)";
+    let n = NFA::from_regex(r).unwrap();
+    let limits = Limits {
+        max_states: Some(100),
+        timeout: None,
+    };
+    assert_eq!(
+        try_determinize(&n, &limits).unwrap_err(),
+        DeterminizeError::TooManyStates {
+            limit: 100,
+            nfa_nodes: n.nodes.len(),
+            regex_len: None,
+        }
+    );
+    let e = DFA::try_from_regex(r, &limits).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        format!(
+            "DFA exceeds 100 states (NFA with {} nodes from a regex of 42 characters)",
+            n.nodes.len()
+        )
+    );
+    let limits = Limits {
+        max_states: None,
+        timeout: Some(Duration::from_secs(0)),
+    };
+    assert!(matches!(
+        try_determinize(&n, &limits),
+        Err(DeterminizeError::Timeout { .. })
+    ));
+    let limits = Limits {
+        max_states: Some(1000),
+        timeout: Some(Duration::from_secs(60)),
+    };
+    assert_eq!(
+        256,
+        try_determinize(&n, &limits).unwrap().minimize().table.len()
+    );
+}
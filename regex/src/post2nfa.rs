@@ -1,7 +1,7 @@
-use crate::regex2post::RegexToken;
+use crate::regex2post::{RegexToken, UnsupportedOperator};
 use crate::nfa::{NFA, NFANode};
 
-pub fn post2nfa(p: &[RegexToken]) -> NFA {
+pub fn post2nfa(p: &[RegexToken]) -> Result<NFA, UnsupportedOperator> {
     let mut stack = Vec::new();
     let mut nfa = NFA::new();
     for t in p {
@@ -38,15 +38,41 @@ pub fn post2nfa(p: &[RegexToken]) -> NFA {
                 nfa.add_edge(e_out, idx);
                 stack.push((idx, idx));
             }
+            RegexToken::And | RegexToken::Not => {
+                return Err(UnsupportedOperator {
+                    operator: if *t == RegexToken::And { '&' } else { '~' },
+                    construction: "Thompson",
+                })
+            }
             RegexToken::Bracket => unreachable!()
         }
     }
     let (a_start, a_out) = stack.pop().unwrap();
     nfa.start = a_start;
-    if nfa.nodes[a_start].accept != None {
+    if nfa.nodes[a_start].accept.is_some() {
         nfa.start = nfa.add_node(NFANode::new(None));
         nfa.add_edge(nfa.start, a_start);
     }
-    nfa.out = a_out;
-    nfa
-}
\ No newline at end of file
+    nfa.out = vec![a_out];
+    Ok(nfa)
+}
+
+#[test]
+fn test_post2nfa_unsupported() {
+    use crate::dfa::DFA;
+    use crate::nfa2dfa::{DeterminizeError, Limits};
+    assert_eq!(
+        NFA::from_regex("a&b").unwrap_err().to_string(),
+        "`&` is not supported by Thompson construction"
+    );
+    assert_eq!(
+        DFA::from_regex("~(ab)").unwrap_err(),
+        UnsupportedOperator {
+            operator: '~',
+            construction: "Thompson"
+        }
+    );    assert!(matches!(
+        DFA::try_from_regex("a|~b", &Limits::default()),
+        Err(DeterminizeError::Unsupported(_))
+    ));
+}
@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter, Result};
+
 #[derive(PartialEq, Debug)]
 pub enum RegexToken {
     Char(char),
@@ -6,6 +8,47 @@ pub enum RegexToken {
     Closure,
     Epsilon,
     Bracket,
+    And,
+    Not,
+}
+
+impl RegexToken {
+    fn precedence(&self) -> usize {
+        match self {
+            RegexToken::Alter => 1,
+            RegexToken::And => 2,
+            RegexToken::Cat => 3,
+            RegexToken::Not => 4,
+            _ => 0,
+        }
+    }
+}
+
+/// An operator that `construction` cannot translate, such as `&` and `~`
+/// outside of the derivative construction.
+#[derive(Debug, PartialEq)]
+pub struct UnsupportedOperator {
+    pub operator: char,
+    pub construction: &'static str,
+}
+
+impl Display for UnsupportedOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "`{}` is not supported by {} construction",
+            self.operator, self.construction
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedOperator {}
+
+fn push_op(stack: &mut Vec<RegexToken>, post: &mut Vec<RegexToken>, op: RegexToken) {
+    while matches!(stack.last(), Some(t) if t.precedence() > op.precedence()) {
+        post.push(stack.pop().unwrap());
+    }
+    stack.push(op);
 }
 
 pub fn regex2post(r: &str) -> Vec<RegexToken> {
@@ -16,11 +59,22 @@ pub fn regex2post(r: &str) -> Vec<RegexToken> {
         match c {
             '|' => {
                 add_cat = false;
-                stack.push(RegexToken::Alter);
+                push_op(&mut stack, &mut post, RegexToken::Alter);
+            }
+            '&' => {
+                add_cat = false;
+                push_op(&mut stack, &mut post, RegexToken::And);
+            }
+            '~' => {
+                if add_cat {
+                    push_op(&mut stack, &mut post, RegexToken::Cat);
+                    add_cat = false;
+                }
+                stack.push(RegexToken::Not);
             }
             '(' => {
                 if add_cat {
-                    stack.push(RegexToken::Cat);
+                    push_op(&mut stack, &mut post, RegexToken::Cat);
                     add_cat = false;
                 }
                 stack.push(RegexToken::Bracket);
@@ -36,14 +90,14 @@ pub fn regex2post(r: &str) -> Vec<RegexToken> {
             }
             'ε' => {
                 if add_cat {
-                    stack.push(RegexToken::Cat);
+                    push_op(&mut stack, &mut post, RegexToken::Cat);
                 }
                 add_cat = true;
                 post.push(RegexToken::Epsilon);
             }
             _ => {
                 if add_cat {
-                    stack.push(RegexToken::Cat);
+                    push_op(&mut stack, &mut post, RegexToken::Cat);
                 }
                 add_cat = true;
                 post.push(RegexToken::Char(c));
@@ -58,7 +112,7 @@ pub fn regex2post(r: &str) -> Vec<RegexToken> {
 
 #[test]
 fn test_regex2post() {
-    use RegexToken::{Alter, Cat, Char, Closure, Epsilon};
+    use RegexToken::{Alter, And, Cat, Char, Closure, Epsilon, Not};
 
     assert_eq!(regex2post("ε"), vec![Epsilon,]);
     assert_eq!(regex2post("ε*"), vec![Epsilon, Closure,]);
@@ -147,4 +201,43 @@ fn test_regex2post() {
             Cat
         ]
     );
-}
\ No newline at end of file
+    assert_eq!(
+        regex2post("a&b|c"),
+        vec![Char('a'), Char('b'), And, Char('c'), Alter]
+    );
+    assert_eq!(
+        regex2post("ab&c"),
+        vec![Char('a'), Char('b'), Cat, Char('c'), And]
+    );
+    assert_eq!(
+        regex2post("~ab"),
+        vec![Char('a'), Not, Char('b'), Cat]
+    );
+    assert_eq!(
+        regex2post("a~b*"),
+        vec![Char('a'), Char('b'), Closure, Not, Cat]
+    );
+    assert_eq!(
+        regex2post("~(a|b)&c"),
+        vec![Char('a'), Char('b'), Alter, Not, Char('c'), And]
+    );
+}
+
+// Concatenation binds tighter than `|`: `ab|c` is `(ab)|c`, not `a(b|c)`.
+#[test]
+fn test_regex2post_precedence() {
+    use RegexToken::{Alter, Cat, Char, Closure};
+
+    assert_eq!(
+        regex2post("ab|c"),
+        vec![Char('a'), Char('b'), Cat, Char('c'), Alter]
+    );
+    assert_eq!(
+        regex2post("a|bc"),
+        vec![Char('a'), Char('b'), Char('c'), Cat, Alter]
+    );
+    assert_eq!(
+        regex2post("ab*|c"),
+        vec![Char('a'), Char('b'), Closure, Cat, Char('c'), Alter]
+    );
+}